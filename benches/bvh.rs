@@ -0,0 +1,69 @@
+//! Compare ray casting against a flat `Vec` of spheres and against a
+//! `Bvh` of the same spheres, on a random-spheres scene.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use lib::sampler::pixel_sampler;
+use lib::*;
+use rand::Rng;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const RAYS: usize = 20_000;
+
+fn random_spheres(count: usize) -> Vec<Sphere> {
+    let mut rng = pixel_sampler(0, 0);
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let side = (count as f64).sqrt();
+    (0..count)
+        .map(|_| {
+            let center = Point3::new(
+                rng.gen_range(-side..side),
+                rng.gen_range(0.0..1.0),
+                rng.gen_range(-side..side),
+            );
+            Sphere::new(center, 0.2, material.clone())
+        })
+        .collect()
+}
+
+fn cast<T: Hittable>(world: &T) -> (Duration, usize) {
+    let mut rng = pixel_sampler(1, 0);
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            let origin = Point3::new(0.0, 2.0, 0.0);
+            let target = Point3::new(rng.gen_range(-20.0..20.0), 0.0, rng.gen_range(-20.0..20.0));
+            Ray::new(origin, target - origin)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .filter(|ray| black_box(world.hit(ray, Interval::new(0.001, f64::MAX))).is_some())
+        .count();
+    (start.elapsed(), hits)
+}
+
+fn main() {
+    println!("{RAYS} rays per run.");
+    for count in [10, 100, 1_000, 10_000] {
+        let flat = random_spheres(count);
+        let start = Instant::now();
+        let bvh = Bvh::new(random_spheres(count));
+        let build = start.elapsed();
+
+        let (flat_time, flat_hits) = cast(&flat);
+        let (bvh_time, bvh_hits) = cast(&bvh);
+        assert_eq!(flat_hits, bvh_hits);
+
+        println!(
+            "{count:>6} spheres: Vec {:>10.2?}, Bvh {:>10.2?} (built in {:.2?}), speedup ×{:.1}",
+            flat_time,
+            bvh_time,
+            build,
+            flat_time.as_secs_f64() / bvh_time.as_secs_f64()
+        );
+    }
+}
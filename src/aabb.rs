@@ -0,0 +1,165 @@
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+/// Axis-aligned bounding box, as one `Interval` per axis.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Aabb {
+        Aabb { x, y, z }
+    }
+
+    /// The empty box, that contains nothing.
+    pub const fn empty() -> Aabb {
+        Aabb::new(Interval::empty(), Interval::empty(), Interval::empty())
+    }
+
+    /// The box that contains everything, around unbounded objects.
+    pub const fn universe() -> Aabb {
+        Aabb::new(
+            Interval::universe(),
+            Interval::universe(),
+            Interval::universe(),
+        )
+    }
+
+    /// Whether the box has a finite size, which the empty box has.
+    /// ```
+    /// use lib::{Aabb, Point3};
+    /// assert! (Aabb::from_points(Point3::zero(), Point3::new(1.0, 2.0, 3.0)).is_bounded());
+    /// assert! (Aabb::empty().is_bounded());
+    /// assert! (!Aabb::universe().is_bounded());
+    /// ```
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|i| i.size() < f64::INFINITY)
+    }
+
+    /// The box with opposite corners `a` and `b`, in any order.
+    /// ```
+    /// use lib::{Aabb, Interval, Point3};
+    /// let bbox = Aabb::from_points(Point3::new(1.0, 0.0, 3.0), Point3::new(0.0, 2.0, -3.0));
+    /// assert! (bbox.x == Interval::new(0.0, 1.0));
+    /// assert! (bbox.z == Interval::new(-3.0, 3.0));
+    /// ```
+    pub fn from_points(a: Point3, b: Point3) -> Aabb {
+        Aabb::new(
+            Interval::new(a.x.min(b.x), a.x.max(b.x)),
+            Interval::new(a.y.min(b.y), a.y.max(b.y)),
+            Interval::new(a.z.min(b.z), a.z.max(b.z)),
+        )
+    }
+
+    /// The smallest box enclosing both `self` and `other`.
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb::new(
+            self.x.union(other.x),
+            self.y.union(other.y),
+            self.z.union(other.z),
+        )
+    }
+
+    /// The smallest box enclosing `self` and the point `p`.
+    pub fn union_point(self, p: Point3) -> Aabb {
+        self.union(Aabb::from_points(p, p))
+    }
+
+    /// The interval of axis `n` (0 is x, 1 is y, 2 is z).
+    pub fn axis(&self, n: usize) -> Interval {
+        match n {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+
+    /// The index of the axis along which the box is the largest.
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        )
+    }
+
+    /// Total area of the six faces, zero for an empty box.
+    /// ```
+    /// use lib::{Aabb, Point3};
+    /// let bbox = Aabb::from_points(Point3::zero(), Point3::new(1.0, 2.0, 3.0));
+    /// assert! (bbox.surface_area() == 22.0);
+    /// assert! (Aabb::empty().surface_area() == 0.0);
+    /// ```
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    /// Pad the box so that no side is narrower than `delta`, to avoid
+    /// degenerate boxes around planar objects.
+    pub fn pad_to_minimums(self, delta: f64) -> Aabb {
+        let pad = |i: Interval| if i.size() < delta { i.expand(delta) } else { i };
+        Aabb::new(pad(self.x), pad(self.y), pad(self.z))
+    }
+
+    /// Determine if `ray` crosses the box within `ray_t` (slab test).
+    /// ```
+    /// use lib::{Aabb, Interval, Point3, Ray, Vec3};
+    /// let bbox = Aabb::from_points(Point3::new(-1.0, -1.0, -3.0), Point3::new(1.0, 1.0, -2.0));
+    /// let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+    /// assert! (bbox.hit(&ray, Interval::positive_or_null()));
+    /// assert! (!bbox.hit(&ray, Interval::new(0.0, 1.0)));
+    /// assert! (!bbox.hit(&Ray::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0)), Interval::universe()));
+    /// ```
+    pub fn hit(&self, ray: &Ray, mut ray_t: Interval) -> bool {
+        for axis in 0..3 {
+            let ax = self.axis(axis);
+            let adinv = 1.0 / ray.direction[axis];
+
+            let t0 = (ax.min - ray.origin[axis]) * adinv;
+            let t1 = (ax.max - ray.origin[axis]) * adinv;
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+            if t0 > ray_t.min {
+                ray_t.min = t0;
+            }
+            if t1 < ray_t.max {
+                ray_t.max = t1;
+            }
+
+            if ray_t.max <= ray_t.min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
@@ -0,0 +1,345 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+/// Number of buckets centroids are sorted into when looking for the
+/// best split.
+const BINS: usize = 12;
+/// Cost of visiting a node, relative to the cost of intersecting an
+/// object.
+const TRAVERSAL_COST: f64 = 0.5;
+/// Leaves with more objects than this are always split, whatever
+/// the heuristic says.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// A bounding volume hierarchy: a tree of nested bounding boxes over
+/// a list of objects, so that a ray only gets tested against the
+/// objects whose boxes it crosses.
+///
+/// The tree is built with the surface area heuristic and stored
+/// flattened, depth first, with objects reordered so that every leaf
+/// refers to a contiguous run of them.  Unbounded objects, like
+/// infinite planes, are kept out of the tree and always tested.
+/// ```
+/// use lib::{Bvh, Hittable, Interval, Lambertian, Point3, Ray, Sphere, Vec3};
+/// use std::sync::Arc;
+///
+/// let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+/// let spheres: Vec<Sphere> = (0..100)
+///     .map(|i| Sphere::new(Point3::new(i as f64, 0.0, -5.0), 0.4, material.clone()))
+///     .collect();
+/// let bvh = Bvh::new(spheres);
+///
+/// let ray = Ray::new(Point3::new(42.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+/// let hit = bvh.hit(&ray, Interval::positive_or_null()).unwrap();
+/// assert! ((hit.t - 4.6).abs() < 1e-9);
+/// ```
+pub struct Bvh<T> {
+    /// Objects of the tree, followed by the unbounded ones
+    objects: Vec<T>,
+    nodes: Vec<Node>,
+    /// Number of objects in the tree
+    bounded: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    bbox: Aabb,
+    /// For leaves, the index of the first object.  For interior
+    /// nodes, the index of the second child (the first child
+    /// immediately follows its parent).
+    offset: usize,
+    /// Number of objects in a leaf, zero for interior nodes.
+    count: usize,
+    /// Axis interior nodes were split along.
+    axis: usize,
+}
+
+/// An object, as seen while building the tree.
+struct Primitive {
+    index: usize,
+    bbox: Aabb,
+    centroid: Point3,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bbox: Aabb,
+    count: usize,
+}
+
+impl<T: Hittable> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Bvh<T> {
+        let (mut primitives, unbounded): (Vec<Primitive>, Vec<Primitive>) = objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let bbox = object.bounding_box();
+                Primitive {
+                    index,
+                    bbox,
+                    centroid: bbox.centroid(),
+                }
+            })
+            .partition(|p| p.bbox.is_bounded());
+
+        let mut nodes = Vec::with_capacity(2 * primitives.len());
+        if !primitives.is_empty() {
+            Self::build(&mut primitives, 0, &mut nodes);
+        }
+
+        // Reorder objects to match the leaves.
+        let mut slots: Vec<Option<T>> = objects.into_iter().map(Some).collect();
+        let objects = primitives
+            .iter()
+            .chain(&unbounded)
+            .map(|p| slots[p.index].take().expect("Each object is used once."))
+            .collect();
+
+        Bvh {
+            objects,
+            nodes,
+            bounded: primitives.len(),
+        }
+    }
+
+    /// Build the subtree for `primitives`, the first of which is
+    /// object number `first`.  Returns the index of the subtree's
+    /// root in `nodes`.
+    fn build(primitives: &mut [Primitive], first: usize, nodes: &mut Vec<Node>) -> usize {
+        let bbox = primitives
+            .iter()
+            .fold(Aabb::empty(), |acc, p| acc.union(p.bbox));
+        let index = nodes.len();
+        nodes.push(Node {
+            bbox,
+            offset: first,
+            count: primitives.len(),
+            axis: 0,
+        });
+
+        let Some((axis, mid)) = Self::split(primitives, &bbox) else {
+            return index;
+        };
+
+        let (left, right) = primitives.split_at_mut(mid);
+        Self::build(left, first, nodes);
+        let second = Self::build(right, first + mid, nodes);
+        nodes[index] = Node {
+            bbox,
+            offset: second,
+            count: 0,
+            axis,
+        };
+        index
+    }
+
+    /// Find the cheapest split of `primitives` according to the
+    /// surface area heuristic, and partition them accordingly.
+    /// Returns the split axis and the size of the first half, or
+    /// `None` if the primitives are better left in a single leaf.
+    fn split(primitives: &mut [Primitive], bbox: &Aabb) -> Option<(usize, usize)> {
+        if primitives.len() <= 1 {
+            return None;
+        }
+
+        let centroids = primitives
+            .iter()
+            .fold(Aabb::empty(), |acc, p| acc.union_point(p.centroid));
+        let axis = centroids.longest_axis();
+        let extent = centroids.axis(axis);
+        if extent.size() <= 0.0 {
+            // All centroids coincide, no split can separate them.
+            return None;
+        }
+
+        let bin_of = |p: &Primitive| {
+            let b = (BINS as f64 * (p.centroid[axis] - extent.min) / extent.size()) as usize;
+            b.min(BINS - 1)
+        };
+
+        let mut bins = [Bin {
+            bbox: Aabb::empty(),
+            count: 0,
+        }; BINS];
+        for p in primitives.iter() {
+            let bin = &mut bins[bin_of(p)];
+            bin.bbox = bin.bbox.union(p.bbox);
+            bin.count += 1;
+        }
+
+        // Cost of splitting after each bin: sweep from the right to
+        // accumulate the right-hand sides, then from the left.
+        let mut right_cost = [0.0; BINS];
+        let mut acc = Bin {
+            bbox: Aabb::empty(),
+            count: 0,
+        };
+        for b in (1..BINS).rev() {
+            acc.bbox = acc.bbox.union(bins[b].bbox);
+            acc.count += bins[b].count;
+            right_cost[b - 1] = acc.count as f64 * acc.bbox.surface_area();
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        let mut acc = Bin {
+            bbox: Aabb::empty(),
+            count: 0,
+        };
+        for b in 0..BINS - 1 {
+            acc.bbox = acc.bbox.union(bins[b].bbox);
+            acc.count += bins[b].count;
+            if acc.count == 0 || acc.count == primitives.len() {
+                continue;
+            }
+            let cost = acc.count as f64 * acc.bbox.surface_area() + right_cost[b];
+            if best.is_none_or(|(_, c)| cost < c) {
+                best = Some((b, cost));
+            }
+        }
+
+        let (best_bin, cost) = best?;
+        let area = bbox.surface_area();
+        let split_cost = TRAVERSAL_COST + if area > 0.0 { cost / area } else { 0.0 };
+        if primitives.len() <= MAX_LEAF_SIZE && split_cost >= primitives.len() as f64 {
+            return None;
+        }
+
+        // Partition in place: primitives up to best_bin go first.
+        let mut mid = 0;
+        for i in 0..primitives.len() {
+            if bin_of(&primitives[i]) <= best_bin {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some((axis, mid))
+    }
+
+    /// The number of objects in the hierarchy.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl<T: Hittable> Hittable for Bvh<T> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let mut ret = None;
+        let mut closest = ray_t.max;
+        for object in &self.objects[self.bounded..] {
+            if let Some(hit) = object.hit(ray, Interval::new(ray_t.min, closest)) {
+                closest = hit.t;
+                ret = Some(hit);
+            }
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            if !node.bbox.hit(ray, Interval::new(ray_t.min, closest)) {
+                continue;
+            }
+
+            if node.count > 0 {
+                for object in &self.objects[node.offset..node.offset + node.count] {
+                    if let Some(hit) = object.hit(ray, Interval::new(ray_t.min, closest)) {
+                        closest = hit.t;
+                        ret = Some(hit);
+                    }
+                }
+            } else if ray.direction[node.axis] < 0.0 {
+                // Visit the nearest child first, so that farther
+                // ones can be culled by `closest`.
+                stack.push(i + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(i + 1);
+            }
+        }
+        ret
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if self.bounded < self.objects.len() {
+            return Aabb::universe();
+        }
+        self.nodes.first().map_or(Aabb::empty(), |n| n.bbox)
+    }
+}
+
+#[test]
+fn bvh_matches_vec_test() {
+    use crate::sampler::pixel_sampler;
+    use crate::{Lambertian, Sphere, Vec3};
+    use rand::Rng;
+    use std::sync::Arc;
+
+    let mut rng = pixel_sampler(1, 0);
+    let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    let spheres = || {
+        let mut rng = pixel_sampler(0, 0);
+        (0..500)
+            .map(|_| {
+                let center = Vec3::random_range(&mut rng, -10.0, 10.0);
+                Sphere::new(center, rng.gen_range(0.05..0.5), material.clone())
+            })
+            .collect::<Vec<Sphere>>()
+    };
+    let flat = spheres();
+    let bvh = Bvh::new(spheres());
+    assert!(bvh.len() == 500);
+    assert!(bvh.bounding_box() == flat.bounding_box());
+
+    for _ in 0..1000 {
+        let ray = Ray::new(
+            Vec3::random_range(&mut rng, -12.0, 12.0),
+            Vec3::random_unit_vector(&mut rng),
+        );
+        let expected = flat.hit(&ray, Interval::new(0.001, f64::MAX)).map(|h| h.t);
+        let actual = bvh.hit(&ray, Interval::new(0.001, f64::MAX)).map(|h| h.t);
+        assert!(expected == actual);
+    }
+}
+
+#[test]
+fn bvh_unbounded_test() {
+    use crate::{Lambertian, Plane, Sphere, Vec3};
+    use std::sync::Arc;
+
+    let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+    let objects: Vec<Box<dyn Hittable>> = vec![
+        Box::new(Sphere::new(
+            Point3::new(0.0, 1.0, 0.0),
+            0.5,
+            material.clone(),
+        )),
+        Box::new(Plane::new(
+            Point3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            material.clone(),
+        )),
+        Box::new(Sphere::new(Point3::new(3.0, 1.0, 0.0), 0.5, material)),
+    ];
+    let bvh = Bvh::new(objects);
+    assert!(!bvh.bounding_box().is_bounded());
+
+    let down = |x| Ray::new(Point3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    let t = |x| {
+        bvh.hit(&down(x), Interval::positive_or_null())
+            .map(|hit| hit.t)
+    };
+    assert!(t(0.0) == Some(3.5));
+    assert!(t(3.0) == Some(3.5));
+    assert!(t(100.0) == Some(5.0));
+}
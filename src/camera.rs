@@ -1,13 +1,19 @@
-use crate::{Color, Hittable, Image, Interval, Point3, Ray, Vec3};
+use crate::integrator::Integrator;
+use crate::sampler::{pixel_sampler, Sampler};
+use crate::{Color, Hittable, Image, Point3, Ray, Vec3};
 use rand::prelude::*;
+use std::sync::Mutex;
+use std::thread;
 
 pub struct Camera {
     /// Rendered image width in pixel count
     image_width: usize,
     /// Count of random samples for each pixel
     samples_per_pixel: usize,
-    /// Maximum number of ray bounces into scene
-    max_depth: usize,
+    /// Number of rendering threads
+    threads: usize,
+    /// Seed of the render's random number generators
+    seed: u64,
     /// Rendered image height
     image_height: usize,
     /// Camera center
@@ -18,103 +24,369 @@ pub struct Camera {
     pixel_delta_u: Vec3,
     /// Offset to pixel below
     pixel_delta_v: Vec3,
+    /// Variation angle of rays through each pixel, in degrees
+    defocus_angle: f64,
+    /// Defocus disk horizontal radius
+    defocus_disk_u: Vec3,
+    /// Defocus disk vertical radius
+    defocus_disk_v: Vec3,
 }
 
-impl Camera {
-    pub fn new(
-        image_width: usize,
-        aspect_ratio: f64,
-        samples_per_pixel: usize,
-        max_depth: usize,
-    ) -> Self {
-        let image_height = (image_width as f64 / aspect_ratio) as usize;
-
-        // Camera
-        let viewport_height = 2.0;
-        let viewport_width = aspect_ratio * viewport_height;
-        let focal_length = 1.0;
-
-        let viewport_u = Vec3::new(viewport_width, 0.0, 0.0);
-        let viewport_v = Vec3::new(0.0, -viewport_height, 0.0);
-
-        let pixel_delta_u = viewport_u / image_width as f64;
+/// Configures and builds a [`Camera`].
+///
+/// The defaults reproduce the historical fixed camera: eye at the
+/// origin, looking down -Z with +Y up, a 90° vertical field of view
+/// and no defocus blur.
+/// ```
+/// use lib::{Camera, Point3, Vec3};
+/// let cam = Camera::builder(400, 16.0 / 9.0)
+///     .lookfrom(Point3::new(-2.0, 2.0, 1.0))
+///     .lookat(Point3::new(0.0, 0.0, -1.0))
+///     .vup(Vec3::new(0.0, 1.0, 0.0))
+///     .vfov(20.0)
+///     .build();
+/// assert_eq!(cam.image_height(), 225);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CameraBuilder {
+    image_width: usize,
+    aspect_ratio: f64,
+    samples_per_pixel: usize,
+    threads: usize,
+    seed: u64,
+    vfov: f64,
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    defocus_angle: f64,
+    focus_dist: Option<f64>,
+}
+
+impl CameraBuilder {
+    pub fn new(image_width: usize, aspect_ratio: f64) -> Self {
+        CameraBuilder {
+            image_width,
+            aspect_ratio,
+            samples_per_pixel: 10,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            vfov: 90.0,
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: None,
+        }
+    }
+
+    /// Rendered image width in pixel count
+    pub fn image_width(mut self, image_width: usize) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    /// Ratio of image width over height
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    /// Count of random samples for each pixel
+    pub fn samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Number of rendering threads.  Defaults to the available
+    /// parallelism of the machine.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Seed of the render.  Two renders of the same scene with the
+    /// same seed are identical, whatever the thread count.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Vertical view angle (field of view), in degrees
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    /// Point the camera is looking from
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    /// Point the camera is looking at
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    /// Camera-relative "up" direction
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    /// Variation angle of rays through each pixel, in degrees.  Zero
+    /// (the default) disables defocus blur.
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    /// Distance from `lookfrom` to the plane of perfect focus.
+    /// Defaults to the distance between `lookfrom` and `lookat`.
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = Some(focus_dist);
+        self
+    }
+
+    /// Build the camera.
+    ///
+    /// # Panics
+    ///
+    /// If `lookfrom` and `lookat` are the same point, or `vup` is
+    /// zero or along the view direction, leaving no camera frame.
+    pub fn build(self) -> Camera {
+        let view = self.lookfrom - self.lookat;
+        assert!(
+            view.length() > 0.0,
+            "Invalid camera looking at its own position {:?}",
+            self.lookfrom
+        );
+        assert!(
+            self.vup.cross(view).length() > 1e-9 * self.vup.length() * view.length(),
+            "Invalid camera vup {:?} along the view direction",
+            self.vup
+        );
+
+        let image_height = ((self.image_width as f64 / self.aspect_ratio) as usize).max(1);
+        let center = self.lookfrom;
+
+        // Determine viewport dimensions.
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (self.lookfrom - self.lookat).length());
+        let theta = self.vfov.to_radians();
+        let h = (theta / 2.0).tan();
+        let viewport_height = 2.0 * h * focus_dist;
+        let viewport_width = viewport_height * (self.image_width as f64 / image_height as f64);
+
+        // Calculate the u,v,w unit basis vectors for the camera coordinate frame.
+        let w = view.unit_vector();
+        let u = self.vup.cross(w).unit_vector();
+        let v = w.cross(u);
+
+        // Calculate the vectors across the horizontal and down the vertical viewport edges.
+        let viewport_u = viewport_width * u;
+        let viewport_v = viewport_height * -v;
+
+        let pixel_delta_u = viewport_u / self.image_width as f64;
         let pixel_delta_v = viewport_v / image_height as f64;
 
         // Calculate the location of the upper left pixel.
-        let center = Point3::zero();
-        let viewport_upper_left =
-            center - Vec3::new(0.0, 0.0, focal_length) - viewport_u / 2.0 - viewport_v / 2.0;
+        let viewport_upper_left = center - (focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
+
+        // Calculate the camera defocus disk basis vectors.
+        let defocus_radius = focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
 
         Camera {
-            image_width,
+            image_width: self.image_width,
             image_height,
-            samples_per_pixel,
-            max_depth,
+            samples_per_pixel: self.samples_per_pixel,
+            threads: self.threads,
+            seed: self.seed,
             center,
             pixel00_loc: viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v),
             pixel_delta_u,
             pixel_delta_v,
+            defocus_angle: self.defocus_angle,
+            defocus_disk_u: u * defocus_radius,
+            defocus_disk_v: v * defocus_radius,
         }
     }
+}
+
+impl Camera {
+    pub fn new(image_width: usize, aspect_ratio: f64, samples_per_pixel: usize) -> Self {
+        Self::builder(image_width, aspect_ratio)
+            .samples_per_pixel(samples_per_pixel)
+            .build()
+    }
+
+    pub fn builder(image_width: usize, aspect_ratio: f64) -> CameraBuilder {
+        CameraBuilder::new(image_width, aspect_ratio)
+    }
 
-    pub fn render<T: Hittable>(&self, world: &T) -> Image {
+    pub fn image_width(&self) -> usize {
+        self.image_width
+    }
+
+    pub fn image_height(&self) -> usize {
+        self.image_height
+    }
+
+    /// Render `world`, with `integrator` computing the light arriving
+    /// along camera rays.  Rows of the image are handed out to the
+    /// rendering threads as they become free.
+    pub fn render<T: Hittable, I: Integrator + ?Sized>(&self, world: &T, integrator: &I) -> Image {
         let mut image = Image::new(self.image_width, self.image_height);
 
-        for j in 0..self.image_height {
-            for i in 0..self.image_width {
-                let mut pixel_color = Vec3::default();
-                for _ in 0..self.samples_per_pixel {
-                    let ray = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&ray, world, self.max_depth);
-                }
-                image[(i, j)] = pixel_color
+        let rows = Mutex::new(image.vec.chunks_mut(self.image_width).enumerate());
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                scope.spawn(|| loop {
+                    // Bind the row first so the lock is released
+                    // before rendering it.
+                    let next = rows.lock().unwrap().next();
+                    let Some((j, row)) = next else {
+                        break;
+                    };
+                    self.render_row(world, integrator, j, row);
+                });
             }
-        }
+        });
+
         image.normalize(self.samples_per_pixel);
-        // image.linear_to_gamma();
         image
     }
 
-    fn get_ray(&self, i: usize, j: usize) -> Ray {
-        let pixel_center =
-            self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
-        let ray_direction = pixel_sample - self.center;
-        Ray::new(self.center, ray_direction)
-    }
-
-    fn ray_color<T: Hittable>(&self, ray: &Ray, world: &T, depth: usize) -> Color {
-        if depth <= 0 {
-            return Color::zero();
+    fn render_row<I: Integrator + ?Sized>(
+        &self,
+        world: &dyn Hittable,
+        integrator: &I,
+        j: usize,
+        row: &mut [Color],
+    ) {
+        for (i, pixel) in row.iter_mut().enumerate() {
+            let mut rng = pixel_sampler(self.seed, j * self.image_width + i);
+            let mut pixel_color = Vec3::default();
+            for _ in 0..self.samples_per_pixel {
+                let ray = self.get_ray(i, j, &mut rng);
+                pixel_color += integrator.radiance(&ray, world, &mut rng);
+            }
+            *pixel = pixel_color
         }
+    }
 
-        let white = Color::new(1.0, 1.0, 1.0);
-        let grad_end = Color::new(0.5, 0.7, 1.0);
-
-        if let Some(hit) = world.hit(ray, Interval::new(0.001, f64::MAX)) {
-            // Naive reflection:
-            // let direction = Vec3::random_on_hemisphere(&hit.normal);
-            // True Lambertian:
-            let direction = hit.normal + Vec3::random_unit_vector();
-            0.5 * self.ray_color(&Ray::new(hit.p, direction), world, depth - 1)
+    /// Get a randomly-sampled camera ray for the pixel at location
+    /// i,j, originating from the camera defocus disk.
+    fn get_ray(&self, i: usize, j: usize, rng: &mut Sampler) -> Ray {
+        let pixel_center =
+            self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
+        let pixel_sample = pixel_center + self.pixel_sample_square(rng);
+        let ray_origin = if self.defocus_angle <= 0.0 {
+            self.center
         } else {
-            let unit_direction = ray.direction.unit_vector();
-            let t = 0.5 * (unit_direction.y + 1.0);
-            (1.0 - t) * white + t * grad_end
-        }
+            self.defocus_disk_sample(rng)
+        };
+        let ray_direction = pixel_sample - ray_origin;
+        Ray::new(ray_origin, ray_direction)
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let mut rng = rand::thread_rng();
-
+    fn pixel_sample_square(&self, rng: &mut Sampler) -> Vec3 {
         let px: f64 = -0.5 + rng.gen::<f64>();
         let py: f64 = -0.5 + rng.gen::<f64>();
         (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
     }
+
+    /// Returns a random point in the camera defocus disk.
+    fn defocus_disk_sample(&self, rng: &mut Sampler) -> Point3 {
+        let p = Vec3::random_in_unit_disk(rng);
+        self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
+    }
 }
 
 #[test]
 fn aspect_ratio_test() {
-    let cam = Camera::new(1000, 16.0 / 9.0);
+    let cam = Camera::builder(1000, 16.0 / 9.0).build();
     assert!(cam.image_height == 562);
 }
+
+#[test]
+fn default_camera_test() {
+    // The default builder must match the historical fixed camera.
+    let cam = Camera::builder(1000, 16.0 / 9.0).build();
+    let viewport_width = 2.0 * 1000.0 / 562.0;
+    let expected = Point3::new(-viewport_width / 2.0, 1.0, -1.0)
+        + 0.5 * (cam.pixel_delta_u + cam.pixel_delta_v);
+    assert!((cam.pixel00_loc - expected).length() < 1e-9);
+    assert!((cam.pixel_delta_u - Vec3::new(viewport_width / 1000.0, 0.0, 0.0)).length() < 1e-9);
+    assert!((cam.pixel_delta_v - Vec3::new(0.0, -2.0 / 562.0, 0.0)).length() < 1e-9);
+}
+
+#[test]
+fn look_at_test() {
+    // Looking down +X from the origin, the central pixel ray must go along +X.
+    let cam = Camera::builder(101, 1.0)
+        .lookat(Point3::new(5.0, 0.0, 0.0))
+        .build();
+    let center = cam.pixel00_loc + 50.0 * (cam.pixel_delta_u + cam.pixel_delta_v);
+    assert!((center.unit_vector() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+    assert!((cam.pixel_delta_u.unit_vector() - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+}
+
+#[test]
+#[should_panic(expected = "Invalid camera looking at its own position")]
+fn look_at_self_test() {
+    Camera::builder(10, 1.0)
+        .lookfrom(Point3::new(1.0, 2.0, 3.0))
+        .lookat(Point3::new(1.0, 2.0, 3.0))
+        .build();
+}
+
+#[test]
+#[should_panic(expected = "Invalid camera vup")]
+fn vup_along_view_test() {
+    Camera::builder(10, 1.0)
+        .lookat(Point3::new(0.0, -2.0, 0.0))
+        .build();
+}
+
+#[test]
+fn defocus_test() {
+    // Without defocus, every ray starts at the camera center; with
+    // it, origins spread over a disk of the expected radius.
+    let sharp = Camera::builder(10, 1.0).build();
+    let mut rng = pixel_sampler(0, 0);
+    assert!(sharp.get_ray(3, 4, &mut rng).origin == Point3::zero());
+
+    let blurry = Camera::builder(10, 1.0)
+        .defocus_angle(10.0)
+        .focus_dist(2.0)
+        .build();
+    let radius = 2.0 * 5.0_f64.to_radians().tan();
+    let origins: Vec<Point3> = (0..100)
+        .map(|_| blurry.get_ray(3, 4, &mut rng).origin)
+        .collect();
+    assert!(origins.iter().all(|o| o.z == 0.0 && o.length() <= radius));
+    assert!(origins.iter().any(|o| *o != Point3::zero()));
+}
+
+#[test]
+fn deterministic_render_test() {
+    use crate::{Lambertian, PathTracer, Sphere};
+    use std::sync::Arc;
+
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let world = vec![
+        Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material.clone()),
+        Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material),
+    ];
+    let builder = Camera::builder(16, 1.0).samples_per_pixel(4).seed(7);
+    let tracer = PathTracer::new(10);
+
+    let single = builder.threads(1).build().render(&world, &tracer);
+    let multi = builder.threads(3).build().render(&world, &tracer);
+    let other = builder.seed(8).threads(3).build().render(&world, &tracer);
+    assert!(single.vec == multi.vec);
+    assert!(single.vec != other.vec);
+}
@@ -0,0 +1,657 @@
+//! Environments: the light arriving from infinitely far away, seen by
+//! rays which escape the scene.
+
+use crate::image::Image;
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::vec3::{Color, Vec3};
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Light arriving from every direction at infinity.  Environments are
+/// shared between rendering threads, hence the `Send + Sync` bound.
+///
+/// Environments concentrating their light in a few directions, like a
+/// sun, can be importance sampled: [`PathTracer`](crate::PathTracer)
+/// then picks directions towards their light at every diffuse bounce,
+/// as it does towards emissive objects.
+pub trait Environment: Send + Sync {
+    /// The light arriving along `-direction`, that is, seen looking
+    /// along `direction`.
+    fn radiance(&self, direction: Vec3) -> Color;
+
+    /// Whether the environment is worth sampling with
+    /// [`random`](Environment::random).
+    fn is_sampled(&self) -> bool {
+        false
+    }
+
+    /// The density, in solid angle, with which
+    /// [`random`](Environment::random) picks `direction`.
+    fn pdf_value(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// A random unit direction, picked more often where more light
+    /// arrives from.
+    fn random(&self, _rng: &mut Sampler) -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+}
+
+impl<T: Environment + ?Sized> Environment for Box<T> {
+    fn radiance(&self, direction: Vec3) -> Color {
+        (**self).radiance(direction)
+    }
+
+    fn is_sampled(&self) -> bool {
+        (**self).is_sampled()
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        (**self).pdf_value(direction)
+    }
+
+    fn random(&self, rng: &mut Sampler) -> Vec3 {
+        (**self).random(rng)
+    }
+}
+
+/// Simple backgrounds, smooth enough not to need sampling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    /// A vertical blend from `bottom`, looking straight down, to
+    /// `top`, looking straight up.
+    Gradient { bottom: Color, top: Color },
+    /// The same color in every direction.
+    Solid(Color),
+    /// No light at all, for scenes lit by their own emissive objects.
+    None,
+}
+
+impl Background {
+    /// The color seen looking along `direction`.
+    /// ```
+    /// use lib::{Background, Color, Vec3};
+    /// let sky = Background::default();
+    /// assert!(sky.color(Vec3::new(0.0, -1.0, 0.0)) == Color::new(1.0, 1.0, 1.0));
+    /// assert!(sky.color(Vec3::new(0.0, 2.0, 0.0)) == Color::new(0.5, 0.7, 1.0));
+    /// ```
+    pub fn color(&self, direction: Vec3) -> Color {
+        match *self {
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.unit_vector().y + 1.0);
+                (1.0 - t) * bottom + t * top
+            }
+            Background::Solid(color) => color,
+            Background::None => Color::zero(),
+        }
+    }
+}
+
+impl Default for Background {
+    /// The white to light blue sky of the book.
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: Color::new(1.0, 1.0, 1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Environment for Background {
+    fn radiance(&self, direction: Vec3) -> Color {
+        self.color(direction)
+    }
+}
+
+/// Picks one of several cells with probability proportional to their
+/// weights.
+struct Distribution {
+    /// Cumulative weights, from 0 to 1, one more than cells.
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution {
+    fn new(weights: &[f64]) -> Distribution {
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+        let mut total = 0.0;
+        for weight in weights {
+            total += weight;
+            cdf.push(total);
+        }
+        if total > 0.0 {
+            cdf.iter_mut().for_each(|c| *c /= total);
+        } else {
+            // Nothing to favor: pick cells uniformly.
+            let n = weights.len() as f64;
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, c)| *c = i as f64 / n);
+        }
+        Distribution { cdf, total }
+    }
+
+    /// Probability of cell `i`.
+    fn probability(&self, i: usize) -> f64 {
+        self.cdf[i + 1] - self.cdf[i]
+    }
+
+    /// The cell `xi`, uniform in [0, 1), falls in, and where it falls
+    /// in that cell, from 0 to 1.
+    fn sample(&self, xi: f64) -> (usize, f64) {
+        let i = (self.cdf.partition_point(|&c| c <= xi) - 1).min(self.cdf.len() - 2);
+        let offset = (xi - self.cdf[i]) / self.probability(i);
+        (i, offset.clamp(0.0, 1.0 - f64::EPSILON))
+    }
+}
+
+/// A panorama in equirectangular (latitude-longitude) projection,
+/// usually a high dynamic range photograph, lighting the scene.
+///
+/// The middle column of the image looks straight ahead (towards -Z),
+/// columns further right look further right, round to the edges which
+/// look behind; rows go from straight up to straight down.
+/// Directions are sampled in proportion to the luminance of the
+/// pixels, so that small bright sources, like the sun, light the
+/// scene with little noise; pixels are not filtered, for sampling to
+/// follow the light exactly.
+/// ```
+/// use lib::{Color, EnvironmentMap, Environment, Image, Vec3};
+/// // Dark above the horizon, bright below.
+/// let image = Image::from_vec(1, 2, vec![Color::zero(), Color::new(1.0, 1.0, 1.0)]);
+/// let map = EnvironmentMap::new(image).intensity(2.0);
+/// assert!(map.radiance(Vec3::new(0.0, -1.0, 0.0)) == Color::new(2.0, 2.0, 2.0));
+/// assert!(map.radiance(Vec3::new(0.0, 1.0, 0.0)) == Color::zero());
+/// ```
+pub struct EnvironmentMap {
+    image: Image,
+    /// Sine and cosine of the rotation about the vertical axis.
+    rotation: (f64, f64),
+    intensity: f64,
+    /// Distribution of the rows, and of the pixels in each row.
+    rows: Distribution,
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    /// Light from the panorama `image`, unrotated and at its own
+    /// intensity.
+    pub fn new(image: Image) -> EnvironmentMap {
+        let (width, height) = (image.width(), image.height());
+        let mut row_weights = Vec::with_capacity(height);
+        let mut columns = Vec::with_capacity(height);
+        for y in 0..height {
+            // Rows near the poles cover less of the sphere.
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            let weights: Vec<f64> = (0..width)
+                .map(|x| luminance(image[(x, y)]).max(0.0) * sin_theta)
+                .collect();
+            let row = Distribution::new(&weights);
+            row_weights.push(row.total);
+            columns.push(row);
+        }
+        EnvironmentMap {
+            rows: Distribution::new(&row_weights),
+            columns,
+            image,
+            rotation: (0.0, 1.0),
+            intensity: 1.0,
+        }
+    }
+
+    /// Turn the panorama by `degrees` counterclockwise about the
+    /// vertical axis, seen from above.
+    pub fn rotation(mut self, degrees: f64) -> Self {
+        self.rotation = degrees.to_radians().sin_cos();
+        self
+    }
+
+    /// Multiply the light of the panorama by `intensity`.
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Turn `direction` by the rotation, or back with `sign` -1.
+    fn rotate(&self, direction: Vec3, sign: f64) -> Vec3 {
+        let (sin, cos) = (sign * self.rotation.0, self.rotation.1);
+        Vec3::new(
+            cos * direction.x + sin * direction.z,
+            direction.y,
+            -sin * direction.x + cos * direction.z,
+        )
+    }
+
+    /// Image coordinates of `direction`, from 0 to 1 left to right and
+    /// top to bottom, and the sine of its angle to the vertical.
+    fn coordinates(&self, direction: Vec3) -> (f64, f64, f64) {
+        let d = self.rotate(direction.unit_vector(), -1.0);
+        let theta = d.y.clamp(-1.0, 1.0).acos();
+        let phi = d.x.atan2(-d.z);
+        (0.5 + phi / (2.0 * PI), theta / PI, theta.sin())
+    }
+
+    /// The pixel at image coordinates `s`, `t`.
+    fn pixel(&self, s: f64, t: f64) -> (usize, usize) {
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = ((s * width as f64) as usize).min(width - 1);
+        let y = ((t * height as f64) as usize).min(height - 1);
+        (x, y)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3) -> Color {
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return Color::zero();
+        }
+        let (s, t, _) = self.coordinates(direction);
+        self.intensity * self.image[self.pixel(s, t)]
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.rows.total > 0.0 && self.intensity > 0.0
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        if !self.is_sampled() {
+            return 0.0;
+        }
+        let (s, t, sin_theta) = self.coordinates(direction);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.pixel(s, t);
+        let pixels = (self.image.width() * self.image.height()) as f64;
+        // Pixels are uniformly sampled in image coordinates, which
+        // map onto 2π² sin θ of solid angle per unit area.
+        let density = self.rows.probability(y) * self.columns[y].probability(x) * pixels;
+        density / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn random(&self, rng: &mut Sampler) -> Vec3 {
+        let (y, dy) = self.rows.sample(rng.gen());
+        let (x, dx) = self.columns[y].sample(rng.gen());
+        let s = (x as f64 + dx) / self.image.width() as f64;
+        let t = (y as f64 + dy) / self.image.height() as f64;
+        let (sin_theta, cos_theta) = (PI * t).sin_cos();
+        let (sin_phi, cos_phi) = (2.0 * PI * (s - 0.5)).sin_cos();
+        let d = Vec3::new(sin_theta * sin_phi, cos_theta, -sin_theta * cos_phi);
+        self.rotate(d, 1.0)
+    }
+}
+
+/// Angular radius of the sun, in radians.
+const SUN_RADIUS: f64 = 0.00465;
+
+/// Illuminance of the sun above the atmosphere, in kilolux.
+const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+/// A clear daylight sky, after the analytic model of Preetham, Shirley
+/// and Smits (1999), with the disk of the sun and a diffuse ground.
+///
+/// The sky is brightest around the sun and near the horizon, and hazier
+/// as the `turbidity` of the air grows, from 2 for a very clear sky to
+/// 10 for a hazy one.  The sun shines through the air as through a
+/// filter, redder and dimmer the lower it is.  Below the horizon lies
+/// an endless ground of color `ground_albedo`, lit by the sun and the
+/// sky.
+///
+/// Radiance is in kilocandelas per square meter, scaled by
+/// [`intensity`](Sky::intensity).  The sun is sampled as a light, so
+/// that sunlit scenes converge quickly.
+/// ```
+/// use lib::{Environment, Sky, Vec3};
+/// let sky = Sky::new(Vec3::new(0.0, 1.0, -1.0));
+/// let zenith = sky.radiance(Vec3::new(0.0, 1.0, 0.0));
+/// let sun = sky.radiance(Vec3::new(0.0, 1.0, -1.0));
+/// assert!(zenith.z > zenith.x && sun.y > 1000.0 * zenith.y);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    sun_direction: Vec3,
+    turbidity: f64,
+    ground_albedo: Color,
+    intensity: f64,
+    /// Perez coefficients A to E of the luminance and chromaticities
+    perez: [[f64; 5]; 3],
+    /// Luminance and chromaticities at the zenith
+    zenith: [f64; 3],
+    /// Radiance of the sun disk and of the ground
+    sun: Color,
+    ground: Color,
+}
+
+impl Sky {
+    /// The sky with the sun in `direction`, which need not be of unit
+    /// length, a turbidity of 3, a ground of albedo 0.3 and an
+    /// intensity of 0.03, which puts the light of a white surface in
+    /// full sun around 1.
+    pub fn new(sun_direction: Vec3) -> Sky {
+        Sky {
+            sun_direction: sun_direction.unit_vector(),
+            turbidity: 3.0,
+            ground_albedo: Color::new(0.3, 0.3, 0.3),
+            intensity: 0.03,
+            perez: [[0.0; 5]; 3],
+            zenith: [0.0; 3],
+            sun: Color::zero(),
+            ground: Color::zero(),
+        }
+        .update()
+    }
+
+    /// The sky with the sun `elevation` degrees above the horizon and
+    /// `azimuth` degrees clockwise from straight ahead (-Z), seen from
+    /// above.
+    pub fn from_angles(elevation: f64, azimuth: f64) -> Sky {
+        let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+        let (sin_azimuth, cos_azimuth) = azimuth.to_radians().sin_cos();
+        Sky::new(Vec3::new(
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+            -cos_elevation * cos_azimuth,
+        ))
+    }
+
+    /// Haziness of the air, clamped to the range of the model, from 2
+    /// to 10.
+    pub fn turbidity(mut self, turbidity: f64) -> Self {
+        self.turbidity = turbidity.clamp(2.0, 10.0);
+        self.update()
+    }
+
+    /// Color of the ground below the horizon.
+    pub fn ground_albedo(mut self, ground_albedo: Color) -> Self {
+        self.ground_albedo = ground_albedo;
+        self.update()
+    }
+
+    /// Multiply the light of the sky, the sun and the ground by
+    /// `intensity`.
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self.update()
+    }
+
+    /// Compute the coefficients of the model from its parameters.
+    fn update(mut self) -> Self {
+        let t = self.turbidity;
+        self.perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // The model only holds for the sun above the horizon.
+        let theta_s = self.sun_direction.y.clamp(0.0, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |c: [[f64; 4]; 3]| {
+            let cubic = |k: [f64; 4]| ((k[0] * theta_s + k[1]) * theta_s + k[2]) * theta_s + k[3];
+            (cubic(c[0]) * t + cubic(c[1])) * t + cubic(c[2])
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        self.zenith = [luminance, x, y];
+
+        // Rayleigh and aerosol (Ångström) extinction of sunlight along
+        // its path through the air, at wavelengths in micrometers
+        // standing for red, green and blue.
+        let sun_illuminance = if self.sun_direction.y > 0.0 {
+            let zenith_angle = theta_s.to_degrees();
+            let air_mass =
+                1.0 / (theta_s.cos() + 0.50572 * (96.07995 - zenith_angle).powf(-1.6364));
+            let beta = 0.04608 * t - 0.04586;
+            let transmittance = |wavelength: f64| {
+                let depth = 0.008735 * wavelength.powf(-4.08) + beta * wavelength.powf(-1.3);
+                (-depth * air_mass).exp()
+            };
+            SOLAR_ILLUMINANCE
+                * Color::new(
+                    transmittance(0.65),
+                    transmittance(0.55),
+                    transmittance(0.45),
+                )
+        } else {
+            Color::zero()
+        };
+        let sun_solid_angle = 2.0 * PI * (1.0 - SUN_RADIUS.cos());
+        self.sun = self.intensity / sun_solid_angle * sun_illuminance;
+
+        // Light falling on the ground, from the sun and from the sky
+        // summed over rings of the upper hemisphere.
+        let (rings, segments) = (32, 64);
+        let mut irradiance = self.sun_direction.y.max(0.0) * sun_illuminance;
+        for i in 0..rings {
+            let theta = 0.5 * PI * (i as f64 + 0.5) / rings as f64;
+            let (sin_theta, cos_theta) = theta.sin_cos();
+            let solid_angle = 0.5 * PI / rings as f64 * 2.0 * PI / segments as f64 * sin_theta;
+            for j in 0..segments {
+                let phi = 2.0 * PI * (j as f64 + 0.5) / segments as f64;
+                let direction = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+                irradiance += cos_theta * solid_angle * self.sky(direction);
+            }
+        }
+        self.ground = self.intensity / PI * self.ground_albedo * irradiance;
+        self
+    }
+
+    /// Light of the sky alone, unscaled, along unit `direction` above
+    /// the horizon.
+    fn sky(&self, direction: Vec3) -> Color {
+        let theta_s = self.sun_direction.y.clamp(0.0, 1.0).acos();
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let perez = |k: [f64; 5], cos_theta: f64, gamma: f64, cos_gamma: f64| {
+            (1.0 + k[0] * (k[1] / cos_theta).exp())
+                * (1.0 + k[2] * (k[3] * gamma).exp() + k[4] * cos_gamma * cos_gamma)
+        };
+        let [luminance, x, y] = std::array::from_fn(|i| {
+            let k = self.perez[i];
+            self.zenith[i] * perez(k, direction.y.max(1e-3), gamma, cos_gamma)
+                / perez(k, 1.0, theta_s, theta_s.cos())
+        });
+
+        // From CIE xyY to linear sRGB
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        let color = Color::new(
+            3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+        );
+        Color::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0))
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let direction = direction.unit_vector();
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+        let mut color = self.intensity * self.sky(direction);
+        if direction.dot(self.sun_direction) >= SUN_RADIUS.cos() {
+            color += self.sun;
+        }
+        color
+    }
+
+    fn is_sampled(&self) -> bool {
+        self.sun != Color::zero()
+    }
+
+    /// Directions are sampled uniformly within the disk of the sun.
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        let cos_sun_radius = SUN_RADIUS.cos();
+        if direction.unit_vector().dot(self.sun_direction) < cos_sun_radius {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_sun_radius))
+    }
+
+    fn random(&self, rng: &mut Sampler) -> Vec3 {
+        let z = 1.0 + rng.gen::<f64>() * (SUN_RADIUS.cos() - 1.0);
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z * z).sqrt();
+        Onb::new(self.sun_direction).transform(Vec3::new(r * phi.cos(), r * phi.sin(), z))
+    }
+}
+
+/// Perceived brightness of linear sRGB `color`.
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+#[test]
+fn environment_map_test() {
+    // Four columns, around from behind: red behind, green to the
+    // left, blue ahead and white to the right.
+    let (red, green, blue) = (
+        Color::new(1.0, 0.0, 0.0),
+        Color::new(0.0, 1.0, 0.0),
+        Color::new(0.0, 0.0, 1.0),
+    );
+    let white = Color::new(1.0, 1.0, 1.0);
+    let image = || Image::from_vec(4, 1, vec![red, green, blue, white]);
+    let ahead = Vec3::new(0.0, 0.0, -1.0);
+    let left = Vec3::new(-1.0, 0.0, 0.0);
+    let map = EnvironmentMap::new(image());
+    assert!(map.radiance(ahead) == blue);
+    assert!(map.radiance(left) == green);
+    assert!(map.radiance(Vec3::new(1.0, 0.0, 0.0)) == white);
+    assert!(map.radiance(Vec3::new(-0.01, 0.0, 1.0)) == red);
+
+    // Turning a quarter counterclockwise brings what was ahead to the
+    // left.
+    let turned = EnvironmentMap::new(image()).rotation(90.0);
+    assert!(turned.radiance(left) == blue);
+    let mut rng = crate::sampler::pixel_sampler(0, 0);
+    for _ in 0..100 {
+        let d = turned.random(&mut rng);
+        assert!((d.length() - 1.0).abs() < 1e-9);
+        assert!(turned.pdf_value(d) > 0.0);
+    }
+}
+
+#[test]
+fn environment_sampling_test() {
+    use crate::sampler::pixel_sampler;
+
+    // A dim sky with a small bright patch, like the sun.
+    let (width, height) = (16, 8);
+    let mut pixels = vec![Color::new(0.1, 0.2, 0.3); width * height];
+    pixels[2 * width + 5] = Color::new(100.0, 90.0, 80.0);
+    let map = EnvironmentMap::new(Image::from_vec(width, height, pixels.clone()));
+    assert!(map.is_sampled());
+
+    // Exact light from the whole sphere: each pixel covers its share
+    // of longitude between the cosines of the angles of its row.
+    let exact: f64 = (0..height)
+        .map(|y| {
+            let cos = |y: usize| (PI * y as f64 / height as f64).cos();
+            let row: f64 = pixels[y * width..(y + 1) * width]
+                .iter()
+                .map(|&c| luminance(c))
+                .sum();
+            row * 2.0 * PI / width as f64 * (cos(y) - cos(y + 1))
+        })
+        .sum();
+
+    // The density integrates to 1, and importance sampling estimates
+    // the light with very little noise.
+    let n = 20000;
+    let mut rng = pixel_sampler(0, 0);
+    let uniform = (0..n)
+        .map(|_| map.pdf_value(Vec3::random_unit_vector(&mut rng)))
+        .sum::<f64>()
+        * 4.0
+        * PI
+        / n as f64;
+    assert!((uniform - 1.0).abs() < 0.05, "{uniform}");
+    let estimate = (0..1000)
+        .map(|_| {
+            let d = map.random(&mut rng);
+            luminance(map.radiance(d)) / map.pdf_value(d)
+        })
+        .sum::<f64>()
+        / 1000.0;
+    assert!(
+        (estimate / exact - 1.0).abs() < 0.01,
+        "{estimate} vs {exact}"
+    );
+
+    let black = EnvironmentMap::new(Image::from_vec(2, 2, vec![Color::zero(); 4]));
+    assert!(!black.is_sampled());
+    assert!(black.pdf_value(Vec3::new(0.0, 1.0, 0.0)) == 0.0);
+}
+
+#[test]
+fn sky_test() {
+    use crate::sampler::pixel_sampler;
+
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let high = Sky::from_angles(60.0, 90.0);
+    let low = Sky::from_angles(5.0, 90.0);
+    let sun = |sky: &Sky| sky.radiance(sky.sun_direction);
+
+    // The sun is where it was put, and reddens as it sets.
+    assert!((high.sun_direction - Vec3::new(0.5, 3f64.sqrt() / 2.0, 0.0)).length() < 1e-9);
+    assert!(sun(&high).y > sun(&low).y);
+    assert!(sun(&low).x / sun(&low).z > sun(&high).x / sun(&high).z);
+    // The clear sky is blue, and paler in haze.
+    let zenith = high.radiance(up);
+    assert!(zenith.z > zenith.x);
+    let hazy = Sky::from_angles(60.0, 90.0).turbidity(9.0).radiance(up);
+    assert!(hazy.x / hazy.z > zenith.x / zenith.z);
+    assert!(sun(&Sky::from_angles(60.0, 90.0).turbidity(9.0)).y < sun(&high).y);
+
+    // The ground reflects the light of the sun and the sky, which is
+    // at least that of the sun alone.
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    let ground = high.radiance(down);
+    let sunlit = high.sun * 2.0 * PI * (1.0 - SUN_RADIUS.cos()) * high.sun_direction.y;
+    assert!(ground.y > 0.3 / PI * sunlit.y && ground.y < 0.3 / PI * 2.0 * sunlit.y);
+    assert!(high.ground_albedo(Color::zero()).radiance(down) == Color::zero());
+
+    // Sampling picks directions within the disk of the sun.
+    let mut rng = pixel_sampler(0, 0);
+    assert!(high.is_sampled());
+    for _ in 0..100 {
+        let d = high.random(&mut rng);
+        assert!(high.pdf_value(d) > 0.0);
+        assert!(high.radiance(d).y >= high.sun.y);
+    }
+    assert!(high.pdf_value(up) == 0.0);
+    assert!(!Sky::from_angles(-10.0, 0.0).is_sampled());
+}
@@ -0,0 +1,244 @@
+//! Minimal OpenEXR encoder: single part, scanline, uncompressed.
+
+use std::io::{self, Write};
+
+/// Precision of the floating point channels of an OpenEXR file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrPrecision {
+    /// 16 bit floats
+    Half,
+    /// 32 bit floats
+    Float,
+}
+
+impl ExrPrecision {
+    fn pixel_type(self) -> i32 {
+        match self {
+            ExrPrecision::Half => 1,
+            ExrPrecision::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ExrPrecision::Half => 2,
+            ExrPrecision::Float => 4,
+        }
+    }
+}
+
+/// Write an OpenEXR image.  `channels` are pairs of a channel name
+/// and its row-major values, and must be sorted by name.
+pub(crate) fn write_exr<W: Write>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    precision: ExrPrecision,
+    channels: &[(&str, Vec<f32>)],
+) -> io::Result<()> {
+    let mut header = Vec::new();
+    // Magic number, then version 2, scanline single part.
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    let mut chlist = Vec::new();
+    for (name, _) in channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&precision.pixel_type().to_le_bytes());
+        // pLinear and three reserved bytes
+        chlist.extend_from_slice(&[0; 4]);
+        // x and y sampling
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+
+    // No compression
+    attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing Y
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // One chunk per scanline, each holding the line number, the data
+    // size, then the channels one after the other.
+    let line_size = width * channels.len() * precision.size();
+    let chunk_size = 8 + line_size;
+    let table_end = header.len() + 8 * height;
+
+    writer.write_all(&header)?;
+    for y in 0..height {
+        writer.write_all(&((table_end + y * chunk_size) as u64).to_le_bytes())?;
+    }
+    for y in 0..height {
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line_size as i32).to_le_bytes())?;
+        for (_, values) in channels {
+            for &v in &values[y * width..(y + 1) * width] {
+                match precision {
+                    ExrPrecision::Half => writer.write_all(&f32_to_f16(v).to_le_bytes())?,
+                    ExrPrecision::Float => writer.write_all(&v.to_le_bytes())?,
+                }
+            }
+        }
+    }
+    writer.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Convert to the bits of an IEEE 754 half precision float, rounding
+/// to nearest even.  Values too large for a half become infinite.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity or NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal half, or zero
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = rest > halfway || (rest == halfway && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+
+    let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+    let rest = mantissa & 0x1fff;
+    // A carry out of the mantissa correctly bumps the exponent.
+    let round = rest > 0x1000 || (rest == 0x1000 && half & 1 == 1);
+    half + round as u16
+}
+
+#[test]
+fn f32_to_f16_test() {
+    assert!(f32_to_f16(0.0) == 0x0000);
+    assert!(f32_to_f16(-0.0) == 0x8000);
+    assert!(f32_to_f16(1.0) == 0x3c00);
+    assert!(f32_to_f16(-2.0) == 0xc000);
+    assert!(f32_to_f16(0.5) == 0x3800);
+    assert!(f32_to_f16(65504.0) == 0x7bff);
+    assert!(f32_to_f16(1e6) == 0x7c00);
+    assert!(f32_to_f16(f32::NAN) & 0x7c00 == 0x7c00);
+    // Smallest subnormal half is 2^-24.
+    assert!(f32_to_f16(2f32.powi(-24)) == 0x0001);
+    assert!(f32_to_f16(2f32.powi(-14)) == 0x0400);
+    // 1 + 2^-11 is halfway between two halves, and rounds to even.
+    assert!(f32_to_f16(1.0 + 2f32.powi(-11)) == 0x3c00);
+    assert!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)) == 0x3c02);
+}
+
+#[test]
+fn write_exr_test() {
+    let (width, height) = (3, 2);
+    let channels = [
+        ("B", (0..6).map(|i| i as f32).collect()),
+        ("G", vec![0.5; 6]),
+        ("R", vec![-2.0; 6]),
+    ];
+    for precision in [ExrPrecision::Half, ExrPrecision::Float] {
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, width, height, precision, &channels).unwrap();
+        assert!(bytes[..4] == [0x76, 0x2f, 0x31, 0x01]);
+        assert!(bytes[4..8] == [2, 0, 0, 0]);
+
+        // Split the header into its attributes.
+        let mut attributes = Vec::new();
+        let mut pos = 8;
+        let string = |pos: &mut usize| {
+            let end = *pos + bytes[*pos..].iter().position(|&b| b == 0).unwrap();
+            let s = std::str::from_utf8(&bytes[*pos..end]).unwrap();
+            *pos = end + 1;
+            s
+        };
+        while bytes[pos] != 0 {
+            let name = string(&mut pos);
+            let kind = string(&mut pos);
+            let size = i32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+            attributes.push((name, kind, &bytes[pos + 4..pos + 4 + size]));
+            pos += 4 + size;
+        }
+        let header_end = pos + 1;
+        let attribute = |name: &str| {
+            let (_, kind, value) = attributes.iter().find(|(n, ..)| *n == name).unwrap();
+            (*kind, *value)
+        };
+
+        let (kind, chlist) = attribute("channels");
+        let mut expected = Vec::new();
+        for name in ["B", "G", "R"] {
+            expected.extend_from_slice(name.as_bytes());
+            expected.push(0);
+            expected.extend_from_slice(&precision.pixel_type().to_le_bytes());
+            expected.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        }
+        expected.push(0);
+        assert!(kind == "chlist" && chlist == expected);
+        assert!(attribute("compression") == ("compression", &[0][..]));
+        let window = [0i32, 0, 2, 1].map(i32::to_le_bytes).concat();
+        assert!(attribute("dataWindow") == ("box2i", &window[..]));
+        assert!(attribute("lineOrder") == ("lineOrder", &[0][..]));
+
+        // The offset table points at each scanline in turn, and the
+        // pixel data fills the rest of the file.
+        let line_size = width * 3 * precision.size();
+        for y in 0..height {
+            let entry = &bytes[header_end + 8 * y..header_end + 8 * y + 8];
+            let offset = u64::from_le_bytes(entry.try_into().unwrap()) as usize;
+            assert!(offset == header_end + 8 * height + y * (8 + line_size));
+            let chunk = &bytes[offset..offset + 8];
+            assert!(chunk[..4] == (y as i32).to_le_bytes());
+            assert!(chunk[4..] == (line_size as i32).to_le_bytes());
+        }
+        assert!(bytes.len() == header_end + height * (8 + 8 + line_size));
+
+        // The second scanline starts with B = 3, 4, 5.
+        let offset = header_end + 8 * height + 8 + line_size + 8;
+        match precision {
+            ExrPrecision::Half => assert!(bytes[offset..offset + 2] == [0x00, 0x42]),
+            ExrPrecision::Float => assert!(bytes[offset..offset + 4] == 3f32.to_le_bytes()),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Radiance RGBE (`.hdr`) encoding and decoding.
+
+use crate::image::ImageError;
+use crate::vec3::Color;
+use std::io::{self, Write};
+
+/// Shortest run worth encoding as such.
+const MIN_RUN: usize = 4;
+
+/// Convert a color to shared-exponent RGBE bytes.
+/// Components are clamped to the largest encodable value, and NaN
+/// components are black.
+pub(crate) fn to_rgbe(color: Color) -> [u8; 4] {
+    let max = 2f64.powi(127) * (255.0 / 256.0);
+    let clamp = |c: f64| if c.is_nan() { 0.0 } else { c.clamp(0.0, max) };
+    let (r, g, b) = (clamp(color.x), clamp(color.y), clamp(color.z));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0; 4];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let e = (v.log2().floor() as i32 + 1).clamp(-128, 127);
+    let scale = 256.0 / 2f64.powi(e);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+/// Convert shared-exponent RGBE bytes back to a color.
+pub(crate) fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::zero();
+    }
+    let f = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(rgbe[0] as f64 * f, rgbe[1] as f64 * f, rgbe[2] as f64 * f)
+}
+
+/// Write a Radiance picture of `width` by `height` pixels, taken in
+/// row-major order from `pixels`.  Scanlines are run-length encoded
+/// when their width allows it.
+pub(crate) fn write_hdr<W: Write>(
+    mut writer: W,
+    width: usize,
+    height: usize,
+    pixels: &[Color],
+) -> io::Result<()> {
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n"
+    )?;
+
+    let rle = (8..0x8000).contains(&width);
+    let mut channel = vec![0; width];
+    for row in pixels.chunks(width) {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(|c| to_rgbe(*c)).collect();
+        if !rle {
+            for pixel in rgbe {
+                writer.write_all(&pixel)?;
+            }
+            continue;
+        }
+
+        writer.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+        for c in 0..4 {
+            for (byte, pixel) in channel.iter_mut().zip(&rgbe) {
+                *byte = pixel[c];
+            }
+            write_rle(&mut writer, &channel)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Run-length encode one channel of a scanline: runs are written as
+/// a count above 128 followed by the repeated byte, other bytes as a
+/// count up to 128 followed by the bytes themselves.
+fn write_rle<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut cur = 0;
+    while cur < data.len() {
+        // Find the next run of at least MIN_RUN bytes.
+        let mut begin = cur;
+        let mut run = 0;
+        let mut previous_run = 0;
+        while run < MIN_RUN && begin < data.len() {
+            begin += run;
+            previous_run = run;
+            run = 1;
+            while begin + run < data.len() && run < 127 && data[begin] == data[begin + run] {
+                run += 1;
+            }
+        }
+
+        // A short run right before the long one is still worth
+        // encoding as a run.
+        if previous_run > 1 && previous_run == begin - cur {
+            writer.write_all(&[128 + previous_run as u8, data[cur]])?;
+            cur = begin;
+        }
+
+        // Bytes up to the start of the run
+        while cur < begin {
+            let count = (begin - cur).min(128);
+            writer.write_all(&[count as u8])?;
+            writer.write_all(&data[cur..cur + count])?;
+            cur += count;
+        }
+
+        if run >= MIN_RUN {
+            writer.write_all(&[128 + run as u8, data[begin]])?;
+            cur += run;
+        }
+    }
+    Ok(())
+}
+
+/// Decode a Radiance picture.  Only the standard `-Y h +X w`
+/// orientation is supported.
+pub(crate) fn read_hdr(bytes: &[u8]) -> Result<(usize, usize, Vec<Color>), ImageError> {
+    let malformed = |msg: &str| ImageError::Malformed(format!("HDR: {msg}"));
+
+    // Header lines, up to an empty line, then the resolution line.
+    let mut pos = 0;
+    let mut line = || {
+        let start = pos;
+        let len = bytes[start..].iter().position(|&b| b == b'\n')?;
+        pos = start + len + 1;
+        std::str::from_utf8(&bytes[start..start + len]).ok()
+    };
+    loop {
+        match line() {
+            None => return Err(malformed("unterminated header")),
+            Some("") => break,
+            Some(l) if l.starts_with("FORMAT=") && l != "FORMAT=32-bit_rle_rgbe" => {
+                return Err(malformed(&format!("unsupported {l}")))
+            }
+            Some(_) => (),
+        }
+    }
+    let resolution = line().ok_or_else(|| malformed("missing resolution"))?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (
+            h.parse::<usize>().map_err(|_| malformed("bad height"))?,
+            w.parse::<usize>().map_err(|_| malformed("bad width"))?,
+        ),
+        _ => return Err(malformed(&format!("unsupported resolution {resolution:?}"))),
+    };
+    if width == 0 || height == 0 {
+        return Err(malformed("empty picture"));
+    }
+
+    // Run-length encoding packs at most 127 pixels of a channel in 2
+    // bytes: anything larger than that cannot be in the file.
+    let remaining = bytes.len() - pos;
+    let count = width
+        .checked_mul(height)
+        .filter(|&count| count / 16 <= remaining)
+        .ok_or_else(|| malformed("truncated pixel data"))?;
+    let mut data = bytes[pos..].iter().copied();
+    let mut next = || data.next().ok_or_else(|| malformed("truncated pixel data"));
+    let mut pixels = Vec::with_capacity(count);
+    let mut channels = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let start = [next()?, next()?, next()?, next()?];
+        let rle = (8..0x8000).contains(&width)
+            && start[0] == 2
+            && start[1] == 2
+            && start[2] < 128
+            && (start[2] as usize) << 8 | start[3] as usize == width;
+
+        if !rle {
+            // Flat scanline: we already read the first pixel.
+            channels[0] = start;
+            for pixel in channels.iter_mut().skip(1) {
+                *pixel = [next()?, next()?, next()?, next()?];
+            }
+        } else {
+            for c in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = next()? as usize;
+                    let (count, run) = if count > 128 {
+                        (count - 128, Some(next()?))
+                    } else {
+                        (count, None)
+                    };
+                    if count == 0 || x + count > width {
+                        return Err(malformed("bad run length"));
+                    }
+                    for pixel in &mut channels[x..x + count] {
+                        pixel[c] = match run {
+                            Some(byte) => byte,
+                            None => next()?,
+                        };
+                    }
+                    x += count;
+                }
+            }
+        }
+        pixels.extend(channels.iter().map(|p| from_rgbe(*p)));
+    }
+    Ok((width, height, pixels))
+}
+
+#[test]
+fn rgbe_test() {
+    assert!(to_rgbe(Color::zero()) == [0, 0, 0, 0]);
+    assert!(to_rgbe(Color::new(1.0, 0.5, 0.0)) == [128, 64, 0, 129]);
+    assert!(to_rgbe(Color::new(0.0, 0.0, 3.0)) == [0, 0, 192, 130]);
+    assert!(from_rgbe([128, 64, 0, 129]) == Color::new(1.0, 0.5, 0.0));
+    assert!(from_rgbe([0, 0, 0, 0]) == Color::zero());
+
+    // Out of range values saturate, NaN is black.
+    assert!(to_rgbe(Color::new(f64::INFINITY, 1.0, 1.0)) == [255, 0, 0, 255]);
+    assert!(to_rgbe(Color::new(1e300, 0.0, 0.0)) == [255, 0, 0, 255]);
+    assert!(to_rgbe(Color::new(f64::NAN, 0.5, f64::NEG_INFINITY)) == [0, 128, 0, 128]);
+    assert!(to_rgbe(Color::new(f64::NAN, f64::NAN, f64::NAN)) == [0; 4]);
+    let mut bytes = Vec::new();
+    write_hdr(&mut bytes, 1, 1, &[Color::new(f64::INFINITY, 1.0, 1.0)]).unwrap();
+    assert!(bytes.ends_with(&[255, 0, 0, 255]));
+}
+
+#[test]
+fn read_hdr_test() {
+    let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+    let read = |resolution: &str, data: &[u8]| {
+        let mut bytes = header.to_vec();
+        bytes.extend_from_slice(resolution.as_bytes());
+        bytes.extend_from_slice(data);
+        read_hdr(&bytes)
+    };
+    let (width, height, pixels) = read("-Y 1 +X 2\n", &[128, 64, 0, 129, 0, 0, 0, 0]).unwrap();
+    assert!((width, height) == (2, 1));
+    assert!(pixels == [Color::new(1.0, 0.5, 0.0), Color::zero()]);
+
+    // Dimensions far beyond the data, or overflowing
+    for resolution in [
+        "-Y 1 +X 2\n",
+        "-Y 100000 +X 100000\n",
+        "-Y 4294967296 +X 4294967296\n",
+    ] {
+        assert!(matches!(
+            read(resolution, &[1, 2, 3, 4]),
+            Err(ImageError::Malformed(_))
+        ));
+    }
+}
@@ -1,47 +1,268 @@
 use crate::Interval;
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{Point3, Vec3};
+use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
 
-#[derive(PartialEq,PartialOrd,Copy,Clone)]
-pub struct Hit {
+#[derive(Copy,Clone)]
+pub struct Hit<'a> {
     pub p: Point3,
     pub normal: Vec3,
+    pub material: &'a dyn Material,
     pub t: f64,
+    /// Surface coordinates of the hit point
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
     }
 
-impl Hit {
+impl<'a> Hit<'a> {
+    /// A hit at distance `t` along `ray`, on a surface with
+    /// `outward_normal` (of unit length) and surface coordinates `u`,
+    /// `v`.
+    pub fn new(ray: &Ray, t: f64, outward_normal: Vec3, material: &'a dyn Material, u: f64, v: f64) -> Hit<'a> {
+        let mut hit = Hit {
+            p: ray.at(t),
+            normal: outward_normal,
+            material,
+            t,
+            u,
+            v,
+            front_face: true,
+        };
+        hit.set_face_normal(ray, &outward_normal);
+        hit
+    }
+
     fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = r.direction.dot(*outward_normal) < 0.0;
         self.normal = if self.front_face { *outward_normal } else {-*outward_normal} ;
     }
 }
 
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit>;
+/// Anything a ray can hit.  Hittables are shared between rendering
+/// threads, hence the `Send + Sync` bound.
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>>;
+
+    /// A box enclosing the whole object.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Probability density, over solid angle, of [`random`] picking
+    /// `direction` from `origin`.  Zero for objects which cannot be
+    /// sampled as lights.
+    ///
+    /// [`random`]: Hittable::random
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// A random direction from `origin` towards the object, to sample
+    /// the light it emits, or `None` if the object cannot be sampled
+    /// from `origin`.
+    fn random(&self, _origin: Point3, _rng: &mut Sampler) -> Option<Vec3> {
+        None
+    }
 }
 
 pub struct Sphere {
     center: Point3,
     radius: f64,
+    material: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64) -> Sphere {
-        Sphere {center, radius }
+    pub fn new(center: Point3, radius: f64, material: Arc<dyn Material>) -> Sphere {
+        Sphere {center, radius, material }
+    }
+
+    /// Surface coordinates of the point at unit vector `p` from the
+    /// center: `u` is the angle around the Y axis from -X, and `v` the
+    /// angle from -Y, both as fractions of their range.
+    fn uv(p: Vec3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Cosine of the half angle of the cone the sphere fills, seen
+    /// from `origin`, or `None` from inside.
+    fn cos_theta_max(&self, origin: Point3) -> Option<f64> {
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        (distance_squared > radius_squared)
+            .then(|| (1.0 - radius_squared / distance_squared).sqrt())
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        (**self).hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        (**self).random(origin, rng)
     }
 }
 
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        (**self).hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        (**self).random(origin, rng)
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for &T {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        (**self).hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        (**self).random(origin, rng)
+    }
+}
+
+/// A list of objects of any kind.
+///
+/// Objects are boxed on insertion; add an `Arc` to share an object
+/// between several lists.
+/// ```
+/// use lib::{Color, Hittable, HittableList, Lambertian, Point3, Sphere};
+/// use std::sync::Arc;
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+/// let shared = Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material.clone()));
+///
+/// let mut group = HittableList::new();
+/// group.add(shared.clone());
+///
+/// let mut world = HittableList::new();
+/// world.add(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material));
+/// world.add(shared);
+/// world.add(group);
+/// let dynamic: Arc<dyn Hittable + Send + Sync> = Arc::new(HittableList::new());
+/// world.add(dynamic);
+/// assert_eq!(world.len(), 4);
+///
+/// let world: &dyn Hittable = &world;
+/// assert!(world.bounding_box().y.min == -200.5);
+/// ```
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl HittableList {
+    pub fn new() -> HittableList {
+        Self::default()
+    }
+
+    pub fn add<T: Hittable + 'static>(&mut self, object: T) {
+        self.bbox = self.bbox.union(object.bounding_box());
+        self.objects.push(Box::new(object));
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.bbox = Aabb::empty();
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Consume the list to build a bounding volume hierarchy over
+    /// its objects.
+    pub fn into_bvh(self) -> Bvh<Box<dyn Hittable>> {
+        Bvh::new(self.objects)
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        self.objects.hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.objects.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        self.objects.random(origin, rng)
+    }
+}
+
+/// As a light, a list picks one of its objects at random.
 impl<T: Hittable> Hittable for Vec<T> {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
         self.iter()
             .filter_map(|x| T::hit(x, ray, ray_t))
             .min_by(|a,b| a.t.partial_cmp(&b.t).expect("Hit.t should compare."))
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.iter().fold(Aabb::empty(), |acc, x| acc.union(x.bounding_box()))
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.iter().map(|x| x.pdf_value(origin, direction)).sum::<f64>() / self.len() as f64
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        if self.is_empty() {
+            return None;
+        }
+        self[rng.gen_range(0..self.len())].random(origin, rng)
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
         let oc = ray.origin - self.center;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(ray.direction);
@@ -63,19 +284,75 @@ impl Hittable for Sphere {
             }
         }
 
-        let point = ray.at(root);
+        let outward_normal = (ray.at(root) - self.center) / self.radius;
+        let (u, v) = Sphere::uv(outward_normal);
+        Some(Hit::new(ray, root, outward_normal, self.material.as_ref(), u, v))
+    }
 
-        // @FIXME This is quite ugly.  We can probably move
-        // everything, incl the call to set_face_normal, into
-        // a Hit::new() constructor and avoid the `let mut`.
-        let mut ret = Hit {
-            t: root,
-            p: point,
-            normal: (point - self.center) / self.radius,
-            front_face: true,
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(self.center - rvec, self.center + rvec)
+    }
+
+    /// Directions are sampled uniformly within the cone the sphere
+    /// fills, seen from outside.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some(cos_theta_max) = self.cos_theta_max(origin) else {
+            return 0.0;
         };
-        let outward_normal = (point - self.center) / self.radius;
-        ret.set_face_normal(ray, &outward_normal);
-        Some(ret)
+        let ray = Ray::new(origin, direction);
+        if self.hit(&ray, Interval::new(0.001, f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        let cos_theta_max = self.cos_theta_max(origin)?;
+        let z = 1.0 + rng.gen::<f64>() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z * z).sqrt();
+        Some(Onb::new(self.center - origin).transform(Vec3::new(r * phi.cos(), r * phi.sin(), z)))
     }
 }
+
+#[test]
+fn sphere_uv_test() {
+    let uv = |x, y, z| Sphere::uv(Vec3::new(x, y, z));
+    assert!(uv(1.0, 0.0, 0.0) == (0.5, 0.5));
+    assert!(uv(0.0, 1.0, 0.0).1 == 1.0 && uv(0.0, -1.0, 0.0).1 == 0.0);
+    assert!(uv(-1.0, 0.0, 0.0) == (0.0, 0.5));
+    assert!(uv(0.0, 0.0, 1.0) == (0.25, 0.5) && uv(0.0, 0.0, -1.0) == (0.75, 0.5));
+}
+
+#[test]
+fn light_mixture_test() {
+    use crate::sampler::pixel_sampler;
+    use crate::{Color, Lambertian};
+
+    // From inside the first lamp, only the second can be sampled: the
+    // mixture then gives no direction half of the time, and estimates
+    // the solid angle of the second lamp without bias.
+    let material = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+    let lamps = vec![
+        Sphere::new(Point3::zero(), 1.0, material.clone()),
+        Sphere::new(Point3::new(4.0, 0.0, 0.0), 2.0, material),
+    ];
+    let origin = Point3::zero();
+    let mut rng = pixel_sampler(0, 0);
+    assert!(lamps[0].random(origin, &mut rng).is_none());
+    let n = 10000;
+    let samples: Vec<Option<Vec3>> = (0..n)
+        .map(|_| lamps.random(origin, &mut rng))
+        .collect();
+    let missing = samples.iter().filter(|d| d.is_none()).count();
+    assert!((missing as f64 / n as f64 - 0.5).abs() < 0.02);
+    let solid_angle = samples
+        .iter()
+        .flatten()
+        .map(|&d| 1.0 / lamps.pdf_value(origin, d))
+        .sum::<f64>()
+        / n as f64;
+    let expected = 2.0 * PI * (1.0 - (1.0 - 0.25f64).sqrt());
+    assert!((solid_angle / expected - 1.0).abs() < 0.03, "{solid_angle}");
+}
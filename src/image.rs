@@ -1,11 +1,120 @@
+use crate::exr::{self, ExrPrecision};
+use crate::hdr;
 use crate::matrix::Matrix;
+use crate::tonemap::{srgb_decode, Look};
 use crate::vec3::Color;
+use std::fmt;
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
 
 pub type Image = Matrix<Color>;
 
+/// Errors when saving or loading an `Image`.
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    PngDecoding(png::DecodingError),
+    /// The file extension or contents don't match any known format.
+    UnsupportedFormat(String),
+    /// The file is of a known format, but its header or data are
+    /// invalid.
+    Malformed(String),
+    /// An extra channel is misnamed or doesn't match the image
+    /// dimensions.
+    InvalidChannel(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "I/O error: {err}"),
+            ImageError::Png(err) => write!(f, "PNG encoding error: {err}"),
+            ImageError::PngDecoding(err) => write!(f, "PNG decoding error: {err}"),
+            ImageError::UnsupportedFormat(ext) => write!(f, "Unsupported image format: {ext:?}"),
+            ImageError::Malformed(msg) => write!(f, "Malformed image: {msg}"),
+            ImageError::InvalidChannel(name) => write!(f, "Invalid channel: {name:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Png(err) => Some(err),
+            ImageError::PngDecoding(err) => Some(err),
+            ImageError::UnsupportedFormat(_)
+            | ImageError::Malformed(_)
+            | ImageError::InvalidChannel(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> Self {
+        ImageError::Png(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        ImageError::PngDecoding(err)
+    }
+}
+
+/// Bits per channel of integer image formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+/// The formats an `Image` can be written as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Plain, ASCII PPM (P3)
+    PlainPpm(BitDepth),
+    /// Binary PPM (P6)
+    Ppm(BitDepth),
+    Png(BitDepth),
+    /// Portable Float Map, 32 bit floats
+    Pfm,
+    /// Radiance RGBE
+    Hdr,
+    Exr(ExrPrecision),
+}
+
+impl ImageFormat {
+    /// The format for a file extension: `.ppm` (binary, 8 bits per
+    /// channel), `.png` (8 bits per channel), `.pfm`, `.hdr` or `.exr`
+    /// (half floats).
+    /// ```
+    /// use lib::{BitDepth, ImageFormat};
+    /// assert_eq!(ImageFormat::from_extension("PNG"), Some(ImageFormat::Png(BitDepth::Eight)));
+    /// assert_eq!(ImageFormat::from_extension("gif"), None);
+    /// ```
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm(BitDepth::Eight)),
+            "png" => Some(ImageFormat::Png(BitDepth::Eight)),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "exr" => Some(ImageFormat::Exr(ExrPrecision::Half)),
+            _ => None,
+        }
+    }
+}
+
 impl Image {
     pub fn normalize(&mut self, samples_per_pixel: usize) {
         let scale = 1.0 / samples_per_pixel as f64;
@@ -17,32 +126,604 @@ impl Image {
         }
     }
 
-    pub fn linear_to_gamma(&mut self) {
-        for element in self.vec.iter_mut() {
-            element.x = element.x.sqrt();
-            element.y = element.y.sqrt();
-            element.z = element.z.sqrt();
+    /// Load an image, recognizing its format from its contents: ASCII
+    /// (P3) or binary (P6) PPM, PNG, PFM or Radiance HDR.  Integer
+    /// formats are assumed to be sRGB encoded and are converted back
+    /// to linear.
+    /// ```no_run
+    /// use lib::Image;
+    /// let image = Image::load("texture.png").unwrap();
+    /// println!("{}×{}", image.width(), image.height());
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, ImageError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    /// Like [`Image::load`], from the contents of an image file.
+    pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+        match bytes {
+            [b'P', b'3', ..] => Self::decode_ppm(bytes, false),
+            [b'P', b'6', ..] => Self::decode_ppm(bytes, true),
+            [b'P', b'F' | b'f', ..] => Self::decode_pfm(bytes),
+            [0x89, b'P', b'N', b'G', ..] => Self::decode_png(bytes),
+            [b'#', b'?', ..] => {
+                let (width, height, pixels) = hdr::read_hdr(bytes)?;
+                Ok(Image::from_vec(width, height, pixels))
+            }
+            _ => Err(ImageError::UnsupportedFormat(
+                String::from_utf8_lossy(&bytes[..bytes.len().min(2)]).into_owned(),
+            )),
+        }
+    }
+
+    fn decode_ppm(bytes: &[u8], binary: bool) -> Result<Image, ImageError> {
+        let mut header = Header::new(bytes, "PPM");
+        header.token()?;
+        let width: usize = header.number("width")?;
+        let height: usize = header.number("height")?;
+        let maxval: u32 = header.number("maximum value")?;
+        if width == 0 || height == 0 || maxval == 0 || maxval > 65535 {
+            return Err(header.malformed("bad dimensions or maximum value"));
         }
+
+        let decode = |v: u32| srgb_decode(v as f64 / maxval as f64);
+        let count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or_else(|| header.malformed("image too large"))?;
+        let samples: Vec<u32> = if binary {
+            // A single whitespace separates the header from the data.
+            let data = bytes
+                .get(header.pos + 1..)
+                .ok_or_else(|| header.malformed("truncated pixel data"))?;
+            let size = if maxval < 256 { 1 } else { 2 };
+            if count.checked_mul(size).is_none_or(|len| data.len() < len) {
+                return Err(header.malformed("truncated pixel data"));
+            }
+            data.chunks(size)
+                .take(count)
+                .map(|b| b.iter().fold(0, |acc, &b| acc << 8 | b as u32))
+                .collect()
+        } else {
+            (0..count)
+                .map(|_| header.number("sample"))
+                .collect::<Result<_, _>>()?
+        };
+        if samples.iter().any(|&v| v > maxval) {
+            return Err(header.malformed("sample above maximum value"));
+        }
+
+        let pixels = samples
+            .chunks(3)
+            .map(|p| Color::new(decode(p[0]), decode(p[1]), decode(p[2])))
+            .collect();
+        Ok(Image::from_vec(width, height, pixels))
+    }
+
+    fn decode_pfm(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut header = Header::new(bytes, "PFM");
+        let channels = if header.token()? == "PF" { 3 } else { 1 };
+        let width: usize = header.number("width")?;
+        let height: usize = header.number("height")?;
+        let scale: f32 = header.number("scale")?;
+        if width == 0 || height == 0 || scale == 0.0 || !scale.is_finite() {
+            return Err(header.malformed("bad dimensions or scale"));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(channels))
+            .ok_or_else(|| header.malformed("image too large"))?;
+        let data = bytes
+            .get(header.pos + 1..)
+            .ok_or_else(|| header.malformed("truncated pixel data"))?;
+        if count.checked_mul(4).is_none_or(|len| data.len() < len) {
+            return Err(header.malformed("truncated pixel data"));
+        }
+        let samples: Vec<f64> = data
+            .chunks(4)
+            .take(count)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                // A negative scale means little-endian samples, and
+                // its magnitude multiplies them.
+                let v = if scale < 0.0 {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                };
+                v as f64 * scale.abs() as f64
+            })
+            .collect();
+
+        // Rows are stored bottom to top.
+        let pixels = samples
+            .chunks(width * channels)
+            .rev()
+            .flat_map(|row| row.chunks(channels))
+            .map(|p| match p {
+                [r, g, b] => Color::new(*r, *g, *b),
+                _ => Color::new(p[0], p[0], p[0]),
+            })
+            .collect();
+        Ok(Image::from_vec(width, height, pixels))
+    }
+
+    fn decode_png(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut decoder = png::Decoder::new(bytes);
+        // Expand palettes and low bit depths to 8 bits per sample.
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+
+        let (color_type, depth) = reader.output_color_type();
+        let samples: Vec<f64> = match depth {
+            png::BitDepth::Sixteen => buf
+                .chunks(2)
+                .map(|b| srgb_decode(u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.0))
+                .collect(),
+            _ => buf.iter().map(|b| srgb_decode(*b as f64 / 255.0)).collect(),
+        };
+
+        // Alpha, if any, is dropped.
+        let pixels = samples
+            .chunks(color_type.samples())
+            .map(|p| match color_type {
+                png::ColorType::Rgb | png::ColorType::Rgba => Color::new(p[0], p[1], p[2]),
+                _ => Color::new(p[0], p[0], p[0]),
+            })
+            .collect();
+        Ok(Image::from_vec(
+            info.width as usize,
+            info.height as usize,
+            pixels,
+        ))
+    }
+
+    /// Save the image to `path` with the default [`Look`], choosing
+    /// the format from the file extension (see
+    /// [`ImageFormat::from_extension`]).
+    /// ```no_run
+    /// use lib::Image;
+    /// let image = Image::new(16, 9);
+    /// image.save("render.png").unwrap();
+    /// assert!(image.save("render.gif").is_err());
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        self.save_with(path, &Look::default())
     }
 
-    /// Write this Matrix as a PPM image path.
-    pub fn write_ppm(self, path: &str) -> std::io::Result<()> {
+    /// Like [`Image::save`], applying `look` for integer formats.
+    /// Float formats are always written linear, untouched.
+    pub fn save_with<P: AsRef<Path>>(&self, path: P, look: &Look) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let format = ImageFormat::from_extension(extension)
+            .ok_or_else(|| ImageError::UnsupportedFormat(extension.to_string()))?;
+        self.write_to_with(format, look, BufWriter::new(File::create(path)?))
+    }
+
+    /// Encode the image as `format` into `writer`, with the default
+    /// [`Look`].  `writer` can be a file, standard output or a
+    /// buffer; wrap unbuffered writers in a `BufWriter`.
+    /// ```
+    /// use lib::{BitDepth, Image, ImageFormat};
+    /// let image = Image::new(2, 1);
+    /// let mut buf = Vec::new();
+    /// image.write_to(ImageFormat::Ppm(BitDepth::Eight), &mut buf).unwrap();
+    /// assert_eq!(buf, b"P6\n2 1\n255\n\0\0\0\0\0\0");
+    /// ```
+    pub fn write_to<W: Write>(&self, format: ImageFormat, writer: W) -> Result<(), ImageError> {
+        self.write_to_with(format, &Look::default(), writer)
+    }
+
+    /// Like [`Image::write_to`], applying `look` for integer formats.
+    pub fn write_to_with<W: Write>(
+        &self,
+        format: ImageFormat,
+        look: &Look,
+        mut writer: W,
+    ) -> Result<(), ImageError> {
+        match format {
+            ImageFormat::PlainPpm(depth) => self.encode_plain_ppm(&mut writer, depth, look)?,
+            ImageFormat::Ppm(depth) => self.encode_ppm(&mut writer, depth, look)?,
+            ImageFormat::Png(depth) => self.encode_png(&mut writer, depth, look)?,
+            ImageFormat::Pfm => self.encode_pfm(&mut writer)?,
+            ImageFormat::Hdr => {
+                hdr::write_hdr(&mut writer, self.width(), self.height(), &self.vec)?
+            }
+            ImageFormat::Exr(precision) => self.encode_exr(&mut writer, precision, &[])?,
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write this Matrix as a plain (ASCII) PPM image path, with the
+    /// default [`Look`].
+    pub fn write_ppm<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        let writer = BufWriter::new(File::create(path)?);
+        self.write_to(ImageFormat::PlainPpm(BitDepth::Eight), writer)
+    }
+
+    /// Write this Matrix as an RGB PNG image with `depth` bits per
+    /// channel, applying `look`.
+    pub fn write_png<P: AsRef<Path>>(
+        &self,
+        path: P,
+        depth: BitDepth,
+        look: &Look,
+    ) -> Result<(), ImageError> {
+        let writer = BufWriter::new(File::create(path)?);
+        self.write_to_with(ImageFormat::Png(depth), look, writer)
+    }
+
+    /// Write this Matrix as a Portable Float Map: linear 32 bit
+    /// floats, unclamped.
+    pub fn write_pfm<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        self.write_to(ImageFormat::Pfm, BufWriter::new(File::create(path)?))
+    }
+
+    /// Write this Matrix as a Radiance RGBE picture.
+    pub fn write_hdr<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        self.write_to(ImageFormat::Hdr, BufWriter::new(File::create(path)?))
+    }
+
+    /// Write this Matrix as an OpenEXR image with `R`, `G` and `B`
+    /// channels.
+    pub fn write_exr<P: AsRef<Path>>(
+        &self,
+        path: P,
+        precision: ExrPrecision,
+    ) -> Result<(), ImageError> {
+        self.write_exr_with_channels(path, precision, &[])
+    }
+
+    /// Write this Matrix as an OpenEXR image with `R`, `G` and `B`
+    /// channels, plus `extra` named channels, such as depth or
+    /// normals.
+    /// ```no_run
+    /// use lib::{ExrPrecision, Image, Matrix};
+    /// let image = Image::new(16, 9);
+    /// let depth: Matrix<f64> = Matrix::new(16, 9);
+    /// image
+    ///     .write_exr_with_channels("render.exr", ExrPrecision::Float, &[("Z", &depth)])
+    ///     .unwrap();
+    /// ```
+    pub fn write_exr_with_channels<P: AsRef<Path>>(
+        &self,
+        path: P,
+        precision: ExrPrecision,
+        extra: &[(&str, &Matrix<f64>)],
+    ) -> Result<(), ImageError> {
         let mut writer = BufWriter::new(File::create(path)?);
-        write!(writer, "P3\n{} {}\n255\n", self.width(), self.height())?;
-        let mut row = 0;
-        for pixel in &self.vec {
-            let red = (255.0 * pixel.x).round() as u8;
-            let green = (255.0 * pixel.y).round() as u8;
-            let blue = (255.0 * pixel.z).round() as u8;
-            write!(writer, "{} {} {}", red, green, blue)?;
-            row += 1;
-            if row == self.width() {
-                write!(writer, "\n")?;
-                row = 0;
-            } else {
-                write!(writer, " ")?;
+        self.encode_exr(&mut writer, precision, extra)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Quantize `look`-mapped channel values to `depth` bits.
+    fn quantize<'a>(&'a self, depth: BitDepth, look: &'a Look) -> impl Iterator<Item = u16> + 'a {
+        let max = match depth {
+            BitDepth::Eight => 255.0,
+            BitDepth::Sixteen => 65535.0,
+        };
+        self.vec
+            .iter()
+            .map(|p| look.apply(*p))
+            .flat_map(|p| [p.x, p.y, p.z])
+            .map(move |c| (max * c).round() as u16)
+    }
+
+    fn encode_plain_ppm<W: Write>(
+        &self,
+        writer: &mut W,
+        depth: BitDepth,
+        look: &Look,
+    ) -> std::io::Result<()> {
+        let maxval = if depth == BitDepth::Eight { 255 } else { 65535 };
+        write!(writer, "P3\n{} {}\n{maxval}\n", self.width(), self.height())?;
+        let samples: Vec<u16> = self.quantize(depth, look).collect();
+        for row in samples.chunks(3 * self.width()) {
+            let mut pixels = row.chunks(3).peekable();
+            while let Some(pixel) = pixels.next() {
+                write!(writer, "{} {} {}", pixel[0], pixel[1], pixel[2])?;
+                if pixels.peek().is_some() {
+                    write!(writer, " ")?;
+                }
             }
+            writeln!(writer)?;
         }
         Ok(())
     }
+
+    fn encode_ppm<W: Write>(
+        &self,
+        writer: &mut W,
+        depth: BitDepth,
+        look: &Look,
+    ) -> std::io::Result<()> {
+        let maxval = if depth == BitDepth::Eight { 255 } else { 65535 };
+        write!(writer, "P6\n{} {}\n{maxval}\n", self.width(), self.height())?;
+        let data: Vec<u8> = match depth {
+            BitDepth::Eight => self.quantize(depth, look).map(|c| c as u8).collect(),
+            // Binary PPM samples are big-endian.
+            BitDepth::Sixteen => self
+                .quantize(depth, look)
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+        };
+        writer.write_all(&data)
+    }
+
+    fn encode_png<W: Write>(
+        &self,
+        writer: W,
+        depth: BitDepth,
+        look: &Look,
+    ) -> Result<(), ImageError> {
+        let mut encoder = png::Encoder::new(writer, self.width() as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        let data: Vec<u8> = match depth {
+            BitDepth::Eight => {
+                encoder.set_depth(png::BitDepth::Eight);
+                self.quantize(depth, look).map(|c| c as u8).collect()
+            }
+            BitDepth::Sixteen => {
+                encoder.set_depth(png::BitDepth::Sixteen);
+                // PNG samples are big-endian.
+                self.quantize(depth, look)
+                    .flat_map(u16::to_be_bytes)
+                    .collect()
+            }
+        };
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn encode_pfm<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        // A negative scale means little-endian samples.
+        write!(writer, "PF\n{} {}\n-1.0\n", self.width(), self.height())?;
+        // Rows are stored bottom to top.
+        for row in self.vec.chunks(self.width()).rev() {
+            for pixel in row {
+                for c in [pixel.x, pixel.y, pixel.z] {
+                    writer.write_all(&(c as f32).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_exr<W: Write>(
+        &self,
+        writer: W,
+        precision: ExrPrecision,
+        extra: &[(&str, &Matrix<f64>)],
+    ) -> Result<(), ImageError> {
+        let planar = |f: &dyn Fn(&Color) -> f64| self.vec.iter().map(|p| f(p) as f32).collect();
+        let mut channels: Vec<(&str, Vec<f32>)> = vec![
+            ("R", planar(&|p| p.x)),
+            ("G", planar(&|p| p.y)),
+            ("B", planar(&|p| p.z)),
+        ];
+        for (name, values) in extra {
+            if name.is_empty()
+                || name.contains('\0')
+                || values.width() != self.width()
+                || values.height() != self.height()
+                || channels.iter().any(|(n, _)| n == name)
+            {
+                return Err(ImageError::InvalidChannel(name.to_string()));
+            }
+            channels.push((name, values.vec.iter().map(|v| *v as f32).collect()));
+        }
+        channels.sort_by(|a, b| a.0.cmp(b.0));
+
+        exr::write_exr(writer, self.width(), self.height(), precision, &channels)?;
+        Ok(())
+    }
+}
+
+/// Reader for the whitespace-separated headers of PPM and PFM files.
+struct Header<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    format: &'static str,
+}
+
+impl<'a> Header<'a> {
+    fn new(bytes: &'a [u8], format: &'static str) -> Self {
+        Header {
+            bytes,
+            pos: 0,
+            format,
+        }
+    }
+
+    fn malformed(&self, msg: &str) -> ImageError {
+        ImageError::Malformed(format!("{}: {msg} (at byte {})", self.format, self.pos))
+    }
+
+    /// Read the next token, skipping whitespace and comments.
+    fn token(&mut self) -> Result<&'a str, ImageError> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(self.malformed("unexpected end of file")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| self.malformed("bad token"))
+    }
+
+    fn number<T: FromStr>(&mut self, what: &str) -> Result<T, ImageError> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| self.malformed(&format!("bad {what} {token:?}")))
+    }
+}
+
+/// A path in the temporary directory, unique to this process so
+/// concurrent test runs don't clobber each other's files.
+#[cfg(test)]
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("rt-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn png_roundtrip_test() {
+    let mut image = Image::new(3, 2);
+    image[(0, 0)] = Color::new(1.0, 0.0, 0.0);
+    image[(2, 1)] = Color::new(0.0, 0.5, 4.0);
+
+    for (depth, name, bytes_per_pixel) in [
+        (BitDepth::Eight, "png-8.png", 3),
+        (BitDepth::Sixteen, "png-16.png", 6),
+    ] {
+        let path = temp_path(name);
+        image.write_png(&path, depth, &Look::default()).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        assert!((info.width, info.height) == (3, 2));
+        assert!(info.buffer_size() == 6 * bytes_per_pixel);
+        assert!(buf[0] == 255 && buf[bytes_per_pixel / 3] == 0);
+        // Last pixel: 0.5 is sRGB encoded, 4.0 clamped.
+        assert!(buf[5 * bytes_per_pixel + bytes_per_pixel / 3] == 188);
+        assert!(buf[6 * bytes_per_pixel - 1] == 255);
+        std::fs::remove_file(path).unwrap();
+    }
+    assert!(matches!(
+        image.save(temp_path("image.gif")),
+        Err(ImageError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn pfm_test() {
+    let mut image = Image::new(2, 2);
+    image[(0, 0)] = Color::new(4.0, 0.0, 0.0);
+    image[(1, 1)] = Color::new(0.0, 0.0, -1.5);
+
+    let path = temp_path("pfm.pfm");
+    image.save(&path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let header = b"PF\n2 2\n-1.0\n";
+    assert!(bytes.starts_with(header));
+    let floats: Vec<f32> = bytes[header.len()..]
+        .chunks(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    // Bottom row first: (0,1), (1,1), then (0,0), (1,0).
+    assert!(floats.len() == 12);
+    assert!(floats[5] == -1.5);
+    assert!(floats[6] == 4.0);
+
+    // Samples are multiplied by the magnitude of the scale.
+    let mut big_endian = b"PF\n1 1\n2.0\n".to_vec();
+    for v in [1.0f32, 0.5, -3.0] {
+        big_endian.extend_from_slice(&v.to_be_bytes());
+    }
+    assert!(Image::decode(&big_endian).unwrap()[(0, 0)] == Color::new(2.0, 1.0, -6.0));
+    let mut little_endian = b"Pf\n1 1\n-0.25\n".to_vec();
+    little_endian.extend_from_slice(&8.0f32.to_le_bytes());
+    assert!(Image::decode(&little_endian).unwrap()[(0, 0)] == Color::new(2.0, 2.0, 2.0));
+}
+
+#[test]
+fn load_test() {
+    let mut image = Image::new(9, 2);
+    image[(0, 0)] = Color::new(0.25, 0.5, 1.0);
+    image[(8, 1)] = Color::new(3.0, 0.0, 0.125);
+
+    let mut plain = Vec::new();
+    image
+        .write_to(ImageFormat::PlainPpm(BitDepth::Sixteen), &mut plain)
+        .unwrap();
+    let loaded = Image::decode(&plain).unwrap();
+    assert!((loaded[(8, 1)] - Color::new(1.0, 0.0, 0.125)).length() < 1e-4);
+
+    let mut p6 = Vec::new();
+    image
+        .write_to(ImageFormat::Ppm(BitDepth::Sixteen), &mut p6)
+        .unwrap();
+    assert!(p6.len() == "P6\n9 2\n65535\n".len() + 9 * 2 * 6);
+    assert!(Image::decode(&p6).unwrap().vec == loaded.vec);
+
+    for (name, tolerance) in [
+        ("load.pfm", 0.0),
+        ("load.hdr", 0.01),
+        ("load.png", 0.005),
+        ("load.ppm", 0.005),
+    ] {
+        let path = temp_path(name);
+        image.save(&path).unwrap();
+        let loaded = Image::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(loaded.width() == 9 && loaded.height() == 2);
+        assert!((loaded[(0, 0)] - image[(0, 0)]).length() <= tolerance);
+        if tolerance == 0.0 {
+            assert!(loaded.vec == image.vec);
+        }
+    }
+
+    // Binary PPM, 16 bits per sample, with a comment
+    let mut p6 = b"P6\n# comment\n1 1\n65535\n".to_vec();
+    p6.extend_from_slice(&[0xff, 0xff, 0, 0, 0x80, 0]);
+    let loaded = Image::decode(&p6).unwrap();
+    assert!(loaded[(0, 0)].x == 1.0 && loaded[(0, 0)].y == 0.0);
+    assert!((loaded[(0, 0)].z - 0.2140482).abs() < 1e-6);
+
+    assert!(matches!(
+        Image::decode(b"P3\n2 x\n255\n"),
+        Err(ImageError::Malformed(_))
+    ));
+    assert!(matches!(
+        Image::decode(b"P6\n2 2\n255\n\0\0"),
+        Err(ImageError::Malformed(_))
+    ));
+    // Headers ending at the end of the file, or with dimensions too
+    // large to count samples
+    for bytes in [
+        &b"P6\n1 1\n255"[..],
+        b"PF\n1 1\n-1.0",
+        b"P6\n4294967296 4294967296\n255\n",
+        b"P3\n4294967296 4294967296\n255\n",
+        b"PF\n4294967296 4294967296\n-1.0\n",
+    ] {
+        assert!(matches!(
+            Image::decode(bytes),
+            Err(ImageError::Malformed(_))
+        ));
+    }
+    assert!(matches!(
+        Image::decode(b"GIF89a"),
+        Err(ImageError::UnsupportedFormat(_))
+    ));
 }
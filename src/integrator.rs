@@ -0,0 +1,440 @@
+//! Light transport: how much light arrives along a camera ray.
+//!
+//! A [`Camera`](crate::Camera) renders a world with any
+//! [`Integrator`]: the [`PathTracer`] for final images, or one of the
+//! quick previews, [`Normals`] and [`AmbientOcclusion`].
+
+use crate::environment::{Background, Environment};
+use crate::hittable::{Hit, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Color, Point3, Vec3};
+use rand::Rng;
+
+/// Computes the light arriving along rays.  Integrators are shared
+/// between rendering threads, hence the `Send + Sync` bound.
+pub trait Integrator: Send + Sync {
+    /// Light arriving along `ray` from `world`, drawing random numbers
+    /// from `rng`.
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, rng: &mut Sampler) -> Color;
+}
+
+impl<T: Integrator + ?Sized> Integrator for Box<T> {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, rng: &mut Sampler) -> Color {
+        (**self).radiance(ray, world, rng)
+    }
+}
+
+/// Unidirectional path tracing, with multiple importance sampling of
+/// the light of emissive objects and of the environment.
+///
+/// Light emitted by the objects a path hits is estimated both by
+/// sampling [`lights`](PathTracer::lights) at every diffuse bounce but
+/// the last and by following scattered rays, the two estimates being
+/// weighted by the power heuristic.  So is the light of a
+/// [`background`](PathTracer::background) which
+/// [is sampled](Environment::is_sampled), like an
+/// [`EnvironmentMap`](crate::EnvironmentMap), one of the lights and
+/// the background being picked at random at each bounce.
+///
+/// Paths are followed in a loop rather than by recursion, so
+/// `max_depth` may be very high.  After a few bounces, Russian
+/// roulette ends paths at random, the more likely the less light they
+/// still carry, and boosts the surviving ones to keep the estimate
+/// unbiased.  Every path has at least a 5% chance to end at each
+/// bounce, so that paths end even between mirrors that lose no light.
+/// ```
+/// use lib::{Background, PathTracer};
+/// let tracer = PathTracer::new(usize::MAX)
+///     .background(Background::None)
+///     .roulette_depth(5);
+/// ```
+pub struct PathTracer {
+    max_depth: usize,
+    roulette_depth: usize,
+    background: Box<dyn Environment>,
+    lights: HittableList,
+}
+
+impl PathTracer {
+    /// A path tracer following rays for up to `max_depth` bounces,
+    /// under the default sky and without lights to sample.
+    pub fn new(max_depth: usize) -> PathTracer {
+        PathTracer {
+            max_depth,
+            roulette_depth: 3,
+            background: Box::new(Background::default()),
+            lights: HittableList::new(),
+        }
+    }
+
+    /// Maximum number of ray bounces into scene
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Number of bounces every path goes through before Russian
+    /// roulette may end it, 3 by default.  `usize::MAX` disables
+    /// Russian roulette, leaving `max_depth` as the only bound on
+    /// path length.
+    pub fn roulette_depth(mut self, roulette_depth: usize) -> Self {
+        self.roulette_depth = roulette_depth;
+        self
+    }
+
+    /// Light of rays escaping the scene, a [`Background`] or any other
+    /// [`Environment`].  Defaults to the book's sky gradient.
+    pub fn background<E: Environment + 'static>(mut self, background: E) -> Self {
+        self.background = Box::new(background);
+        self
+    }
+
+    /// Objects whose light is sampled directly, usually emissive
+    /// spheres and quads also in the world, shared through an `Arc`.
+    /// Objects which cannot be sampled are only found by bouncing
+    /// rays.
+    pub fn lights(mut self, lights: HittableList) -> Self {
+        self.lights = lights;
+        self
+    }
+
+    /// Density with which light sampling picks `direction` from
+    /// `origin`: a mixture of the lights and of the background, when
+    /// it is sampled.
+    fn light_pdf(&self, origin: Point3, direction: Vec3) -> f64 {
+        match (self.lights.is_empty(), self.background.is_sampled()) {
+            (false, false) => self.lights.pdf_value(origin, direction),
+            (true, true) => self.background.pdf_value(direction),
+            (false, true) => {
+                0.5 * (self.lights.pdf_value(origin, direction)
+                    + self.background.pdf_value(direction))
+            }
+            (true, false) => 0.0,
+        }
+    }
+
+    /// Light reflected along `ray` at `hit`, from a point picked on
+    /// one of the lights or from a direction picked on the
+    /// background.
+    fn sample_light(&self, ray: &Ray, hit: &Hit, world: &dyn Hittable, rng: &mut Sampler) -> Color {
+        let on_background = match (self.lights.is_empty(), self.background.is_sampled()) {
+            (_, false) => false,
+            (true, true) => true,
+            (false, true) => rng.gen(),
+        };
+        let direction = if on_background {
+            self.background.random(rng)
+        } else {
+            // The light picked may not be reachable from here, like a
+            // lamp around the point.
+            match self.lights.random(hit.p, rng) {
+                Some(direction) => direction,
+                None => return Color::zero(),
+            }
+        };
+        let light_pdf = self.light_pdf(hit.p, direction);
+        if light_pdf <= 0.0 {
+            return Color::zero();
+        }
+        let reflected = hit.material.eval(ray, hit, direction);
+        if reflected == Color::zero() {
+            return Color::zero();
+        }
+
+        // The light may be hidden behind another object, whose own
+        // light is then what arrives.
+        let shadow_ray = Ray::new(hit.p, direction);
+        let emitted = match world.hit(&shadow_ray, Interval::new(0.001, f64::MAX)) {
+            Some(light_hit) => light_hit.material.emitted(&light_hit),
+            None => self.background.radiance(direction),
+        };
+        let weight = power_heuristic(light_pdf, hit.material.scattering_pdf(ray, hit, direction));
+        weight / light_pdf * reflected * emitted
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(10)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, rng: &mut Sampler) -> Color {
+        let mut color = Color::zero();
+        // Fraction of the light arriving along the current ray which
+        // makes it to the camera
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // Density with which the current ray was scattered, or `None`
+        // for the camera ray and specular bounces, whose light was not
+        // sampled.
+        let mut scattering_pdf = None;
+
+        for depth in 0..self.max_depth {
+            // Light found by following a scattered ray might also have
+            // been sampled.
+            let weight = |emitted: Color| match scattering_pdf {
+                Some(pdf) if emitted != Color::zero() => {
+                    power_heuristic(pdf, self.light_pdf(ray.origin, ray.direction)) * emitted
+                }
+                _ => emitted,
+            };
+            let Some(hit) = world.hit(&ray, Interval::new(0.001, f64::MAX)) else {
+                color += throughput * weight(self.background.radiance(ray.direction));
+                break;
+            };
+            color += throughput * weight(hit.material.emitted(&hit));
+
+            let Some((attenuation, scattered)) = hit.material.scatter(&ray, &hit, rng) else {
+                break;
+            };
+            let pdf = hit.material.scattering_pdf(&ray, &hit, scattered.direction);
+            // Sampled light is weighted assuming the scattered ray is
+            // followed too, which it is not at the last bounce.
+            if pdf > 0.0 && depth + 1 < self.max_depth {
+                color += throughput * self.sample_light(&ray, &hit, world, rng);
+            }
+            throughput *= attenuation;
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            scattering_pdf = (pdf > 0.0).then_some(pdf);
+            ray = scattered;
+        }
+        color
+    }
+}
+
+/// Weight of a sample drawn with density `pdf`, where another
+/// strategy would have drawn it with density `other_pdf`.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    pdf * pdf / (pdf * pdf + other_pdf * other_pdf)
+}
+
+/// Shows the surface normals of the objects rays hit, mapping each
+/// coordinate from [-1, 1] to [0, 1], on black.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, _rng: &mut Sampler) -> Color {
+        match world.hit(ray, Interval::new(0.001, f64::MAX)) {
+            Some(hit) => 0.5 * (hit.normal + Color::new(1.0, 1.0, 1.0)),
+            None => Color::zero(),
+        }
+    }
+}
+
+/// Shows how open the surroundings of the points rays hit are: white
+/// where nothing lies within `distance` above the surface, darker in
+/// creases and corners.  Each sample casts one cosine-distributed
+/// ray; rays escaping the scene are white.
+#[derive(Clone, Copy, Debug)]
+pub struct AmbientOcclusion {
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    /// Occlusion by objects within `distance` of the surface, which
+    /// may be infinite.
+    pub fn new(distance: f64) -> AmbientOcclusion {
+        AmbientOcclusion { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, rng: &mut Sampler) -> Color {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let Some(hit) = world.hit(ray, Interval::new(0.001, f64::MAX)) else {
+            return white;
+        };
+        let mut direction = hit.normal + Vec3::random_unit_vector(rng);
+        if direction.near_zero() {
+            direction = hit.normal;
+        }
+        let probe = Ray::new(hit.p, direction.unit_vector());
+        match world.hit(&probe, Interval::new(0.001, self.distance)) {
+            Some(_) => Color::zero(),
+            None => white,
+        }
+    }
+}
+
+#[test]
+fn light_sampling_test() {
+    use crate::{Camera, Emissive, Image, Lambertian, Point3, Quad, Sphere};
+    use std::sync::Arc;
+
+    // A floor lit by a small spherical lamp and a square panel, both
+    // out of view: sampling the lights must not change the picture on
+    // average, only its noise, whatever the number of bounces.  With a
+    // single one, the lamps are never seen.
+    let grey = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let lamp = Arc::new(Emissive::new(Color::new(1.0, 0.9, 0.8), 20.0));
+    let bulb = Arc::new(Sphere::new(Point3::new(0.5, 2.0, -1.0), 0.2, lamp.clone()));
+    let panel = Arc::new(Quad::new(
+        Point3::new(-1.5, 2.0, -1.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        lamp,
+    ));
+    let mut world = HittableList::new();
+    world.add(Quad::new(
+        Point3::new(-5.0, -0.5, 0.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -10.0),
+        grey.clone(),
+    ));
+    world.add(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, grey));
+    world.add(bulb.clone());
+    world.add(panel.clone());
+    let lights = || {
+        let mut lights = HittableList::new();
+        lights.add(bulb.clone());
+        lights.add(panel.clone());
+        lights
+    };
+
+    let camera = Camera::builder(8, 1.0).samples_per_pixel(400).build();
+    let mean = |image: &Image| image.vec.iter().map(|c| c.x + c.y + c.z).sum::<f64>();
+    for max_depth in [1, 2, 4] {
+        let tracer = PathTracer::new(max_depth).background(Background::None);
+        let unsampled = mean(&camera.render(&world, &tracer));
+        let sampled = mean(&camera.render(&world, &tracer.lights(lights())));
+        assert!(
+            (sampled - unsampled).abs() <= 0.05 * unsampled,
+            "{sampled} vs {unsampled} at depth {max_depth}"
+        );
+    }
+}
+
+#[test]
+fn environment_sampling_test() {
+    use crate::{Camera, EnvironmentMap, Image, Lambertian, Point3, Quad};
+    use std::sync::Arc;
+
+    // A floor under a dim sky with a bright patch, out of view.
+    let sky = || {
+        let mut pixels = vec![Color::new(0.2, 0.2, 0.3); 32];
+        pixels[3] = Color::new(400.0, 300.0, 200.0);
+        EnvironmentMap::new(Image::from_vec(8, 4, pixels))
+    };
+    // The same sky, only found by bouncing rays
+    struct Unsampled(EnvironmentMap);
+    impl Environment for Unsampled {
+        fn radiance(&self, direction: Vec3) -> Color {
+            self.0.radiance(direction)
+        }
+    }
+    let grey = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let world = Quad::new(
+        Point3::new(-50.0, -0.5, 50.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -100.0),
+        grey,
+    );
+
+    let camera = Camera::builder(8, 1.0)
+        .samples_per_pixel(400)
+        .lookat(Point3::new(0.0, -1.0, -0.5))
+        .build();
+    let mean = |image: &Image| image.vec.iter().map(|c| c.x + c.y + c.z).sum::<f64>();
+    let unsampled = mean(&camera.render(&world, &PathTracer::new(4).background(Unsampled(sky()))));
+    let sampled = mean(&camera.render(&world, &PathTracer::new(4).background(sky())));
+    assert!(
+        (sampled / unsampled - 1.0).abs() < 0.05,
+        "{sampled} vs {unsampled}"
+    );
+}
+
+#[test]
+fn preview_test() {
+    use crate::sampler::pixel_sampler;
+    use crate::{Lambertian, Plane, Point3};
+    use std::sync::Arc;
+
+    // Looking down at a floor, next to a wall on the right.
+    let grey = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let world = vec![
+        Plane::new(
+            Point3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            grey.clone(),
+        ),
+        Plane::new(Point3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), grey),
+    ];
+    let down = Ray::new(Point3::zero(), Vec3::new(0.0, -1.0, 0.0));
+    let up = Ray::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0));
+    let mut rng = pixel_sampler(0, 0);
+
+    assert!(
+        (Normals.radiance(&down, &world, &mut rng) - Color::new(0.5, 1.0, 0.5)).length() < 1e-9
+    );
+    assert!(Normals.radiance(&up, &world, &mut rng) == Color::zero());
+
+    // The wall hides half of the sky from the floor, but only from
+    // afar.
+    let n = 10000;
+    let near = AmbientOcclusion::new(0.1);
+    let far = AmbientOcclusion::new(f64::INFINITY);
+    let open = |ao: AmbientOcclusion, rng: &mut Sampler| {
+        (0..n)
+            .map(|_| ao.radiance(&down, &world, rng).x)
+            .sum::<f64>()
+            / n as f64
+    };
+    assert!(open(near, &mut rng) == 1.0);
+    assert!((open(far, &mut rng) - 0.5).abs() < 0.02);
+    assert!(far.radiance(&up, &world, &mut rng) == Color::new(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn roulette_test() {
+    use crate::{Camera, Hit, Image, Lambertian, Material, Metal, Point3, Sphere};
+    use std::sync::Arc;
+
+    // Inside a closed sphere which both reflects a fraction `a` of the
+    // light and emits 1, radiance adds up to 1 / (1 - a) over unending
+    // bounces.
+    struct Glowing(Lambertian);
+    impl Material for Glowing {
+        fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Sampler) -> Option<(Color, Ray)> {
+            self.0.scatter(ray, hit, rng)
+        }
+
+        fn emitted(&self, _hit: &Hit) -> Color {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+    let a = 0.9;
+    let material = Arc::new(Glowing(Lambertian::new(Color::new(a, a, a))));
+    let world = Sphere::new(Point3::zero(), 1.0, material);
+
+    let camera = Camera::builder(8, 1.0).samples_per_pixel(100).build();
+    let mean = |image: &Image| image.vec.iter().map(|c| c.y).sum::<f64>() / image.vec.len() as f64;
+    let unending = mean(&camera.render(&world, &PathTracer::new(usize::MAX)));
+    assert!((unending * (1.0 - a) - 1.0).abs() < 0.05, "{unending}");
+
+    // Without Russian roulette, paths stop at the maximum depth.
+    let tracer = PathTracer::new(5).roulette_depth(usize::MAX);
+    let short = mean(&camera.render(&world, &tracer));
+    let expected = (1.0 - a.powi(5)) / (1.0 - a);
+    assert!((short - expected).abs() < 1e-9, "{short}");
+
+    // Paths end inside a perfect mirror too, which never loses light.
+    let mirror = Sphere::new(
+        Point3::zero(),
+        1.0,
+        Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0)),
+    );
+    let tracer = PathTracer::new(usize::MAX).background(Background::None);
+    assert!(mean(&camera.render(&mirror, &tracer)) == 0.0);
+}
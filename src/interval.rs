@@ -1,8 +1,8 @@
-#[derive(PartialEq, PartialOrd, Copy, Clone)]
+#[derive(PartialEq, PartialOrd, Copy, Clone, Debug)]
 /// @FIXME We'r reimplementing Rust's Range
 pub struct Interval {
-    min: f64,
-    max: f64,
+    pub min: f64,
+    pub max: f64,
 }
 
 impl Interval {
@@ -70,6 +70,37 @@ impl Interval {
         self.min < x && x < self.max
     }
 
+    /// The length of the `Interval`, negative if it is empty.
+    /// ```
+    /// use lib::Interval;
+    /// assert! (Interval::new(1.0, 3.0).size() == 2.0);
+    /// assert! (Interval::empty().size() < 0.0);
+    /// ```
+    pub fn size(&self) -> f64 {
+        self.max - self.min
+    }
+
+    /// The smallest `Interval` enclosing both `self` and `other`.
+    /// ```
+    /// use lib::Interval;
+    /// let a = Interval::new(1.0, 2.0);
+    /// assert! (a.union(Interval::new(3.0, 4.0)) == Interval::new(1.0, 4.0));
+    /// assert! (a.union(Interval::empty()) == a);
+    /// ```
+    pub fn union(self, other: Interval) -> Interval {
+        Interval::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Pad the `Interval` by `delta`, half on each side.
+    /// ```
+    /// use lib::Interval;
+    /// assert! (Interval::new(1.0, 2.0).expand(1.0) == Interval::new(0.5, 2.5));
+    /// ```
+    pub fn expand(self, delta: f64) -> Interval {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     /// ```
     /// use lib::Interval;
     /// let interval = Interval::new(1.0, 2.0);
@@ -1,15 +1,48 @@
+pub mod aabb;
+pub mod bvh;
 pub mod camera;
+pub mod environment;
+mod exr;
+mod hdr;
 pub mod hittable;
 pub mod image;
+pub mod integrator;
 pub mod matrix;
+pub mod mesh;
+pub mod obj;
+pub mod onb;
+pub mod planar;
 pub mod interval;
+pub mod material;
 pub mod ray;
+pub mod sampler;
+pub mod scene_file;
+pub mod scenes;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
 
-pub use camera::Camera;
-pub use hittable::{Hittable,Hit,Sphere};
+pub use aabb::Aabb;
+pub use bvh::Bvh;
+pub use camera::{Camera, CameraBuilder};
+pub use hittable::{Hittable,HittableList,Hit,Sphere};
 pub use interval::Interval;
-pub use image::Image;
+pub use material::{Dielectric, Emissive, Lambertian, Material, Metal};
+pub use exr::ExrPrecision;
+pub use image::{BitDepth, Image, ImageError, ImageFormat};
+pub use environment::{Background, Environment, EnvironmentMap, Sky};
+pub use integrator::{AmbientOcclusion, Integrator, Normals, PathTracer};
 pub use matrix::Matrix;
+pub use mesh::{Face, Mesh, MeshData};
+pub use obj::ObjError;
+pub use onb::Onb;
+pub use planar::{BoxShape, Disk, Plane, Quad};
 pub use ray::Ray;
+pub use scene_file::{SceneError, SceneFile};
+pub use texture::{Checker, ImageTexture, NoiseKind, NoiseTexture, Perlin, SolidColor, Texture, Wrap};
+pub use tonemap::{Look, ToneMap};
+pub use transform::{Transform, Transformed};
+pub use triangle::Triangle;
 pub use vec3::{Color,Point3,Vec3};
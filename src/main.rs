@@ -0,0 +1,291 @@
+use lib::scenes::{self, IntegratorKind, RenderSettings, SCENES};
+use lib::{BitDepth, CameraBuilder, ExrPrecision, ImageFormat, PathTracer, SceneFile};
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: raytrace [OPTIONS] [SCENE]
+
+Render SCENE (default: diffuse) to an image file.  SCENE is either the
+name of a built-in scene or the path of a .toml scene file; options
+given on the command line override the camera of scene files.
+
+Options:
+  -w, --width N           Image width in pixels (default: 1000)
+  -a, --aspect-ratio R    Width over height, as 1.5 or 16:9 (default: 16:9)
+  -n, --samples N         Samples per pixel (default: 10)
+  -d, --max-depth N       Maximum number of ray bounces (default: 10)
+  -i, --integrator NAME   Light transport: path (default) for path tracing,
+                          normals, or ao for ambient occlusion, by objects
+                          within a distance D with ao:D
+      --seed N            Seed of the random number generators (default: 0)
+  -j, --threads N         Number of rendering threads (default: all cores)
+  -o, --output PATH       Output file, or - for standard output
+                          (default: SCENE.png)
+  -f, --format FORMAT     Output format, instead of guessing from the
+                          output extension: ppm, ppm16, plain-ppm, png,
+                          png16, pfm, hdr, exr or exr32
+  -l, --list              List the built-in scenes
+  -h, --help              Print this help
+";
+
+struct Options {
+    settings: RenderSettings,
+    /// Settings explicitly given on the command line
+    width: Option<usize>,
+    aspect_ratio: Option<f64>,
+    samples_per_pixel: Option<usize>,
+    max_depth: Option<usize>,
+    scene: String,
+    output: Option<String>,
+    format: Option<ImageFormat>,
+}
+
+enum Command {
+    Render(Options),
+    List,
+    Help,
+}
+
+fn parse_format(name: &str) -> Option<ImageFormat> {
+    match name {
+        "plain-ppm" => Some(ImageFormat::PlainPpm(BitDepth::Eight)),
+        "ppm16" => Some(ImageFormat::Ppm(BitDepth::Sixteen)),
+        "png16" => Some(ImageFormat::Png(BitDepth::Sixteen)),
+        "exr32" => Some(ImageFormat::Exr(ExrPrecision::Float)),
+        _ => ImageFormat::from_extension(name),
+    }
+}
+
+fn parse_integrator(name: &str) -> Option<IntegratorKind> {
+    match name.split_once(':') {
+        Some(("ao", distance)) => {
+            let distance = distance.parse::<f64>().ok()?;
+            (distance > 0.0).then_some(IntegratorKind::AmbientOcclusion(distance))
+        }
+        Some(_) => None,
+        None => match name {
+            "path" => Some(IntegratorKind::PathTracer),
+            "normals" => Some(IntegratorKind::Normals),
+            "ao" => Some(IntegratorKind::AmbientOcclusion(f64::INFINITY)),
+            _ => None,
+        },
+    }
+}
+
+fn parse_aspect_ratio(value: &str) -> Option<f64> {
+    let ratio = match value.split_once(':') {
+        Some((w, h)) => w.parse::<f64>().ok()? / h.parse::<f64>().ok()?,
+        None => value.parse().ok()?,
+    };
+    (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut options = Options {
+        settings: RenderSettings::default(),
+        width: None,
+        aspect_ratio: None,
+        samples_per_pixel: None,
+        max_depth: None,
+        scene: String::from("diffuse"),
+        output: None,
+        format: None,
+    };
+    let mut scene = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {name}"))
+        };
+        let number = |name: &str, value: String| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid {name}: {value:?}"))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-l" | "--list" => return Ok(Command::List),
+            "-w" | "--width" => options.width = Some(number(&arg, value(&arg)?)?),
+            "-n" | "--samples" => options.samples_per_pixel = Some(number(&arg, value(&arg)?)?),
+            "-d" | "--max-depth" => options.max_depth = Some(number(&arg, value(&arg)?)?),
+            "-j" | "--threads" => options.settings.threads = number(&arg, value(&arg)?)?,
+            "--seed" => {
+                let seed = value(&arg)?;
+                options.settings.seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid {arg}: {seed:?}"))?;
+            }
+            "-a" | "--aspect-ratio" => {
+                let ratio = value(&arg)?;
+                options.aspect_ratio = Some(
+                    parse_aspect_ratio(&ratio)
+                        .ok_or_else(|| format!("Invalid {arg}: {ratio:?}"))?,
+                );
+            }
+            "-i" | "--integrator" => {
+                let name = value(&arg)?;
+                options.settings.integrator = parse_integrator(&name)
+                    .ok_or_else(|| format!("Unknown integrator: {name:?}"))?;
+            }
+            "-o" | "--output" => options.output = Some(value(&arg)?),
+            "-f" | "--format" => {
+                let format = value(&arg)?;
+                options.format = Some(
+                    parse_format(&format).ok_or_else(|| format!("Unknown format: {format:?}"))?,
+                );
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("Unknown option: {arg}"))
+            }
+            _ if scene.is_none() => scene = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+
+    if let Some(scene) = scene {
+        options.scene = scene;
+    }
+    let settings = &mut options.settings;
+    settings.image_width = options.width.unwrap_or(settings.image_width);
+    settings.aspect_ratio = options.aspect_ratio.unwrap_or(settings.aspect_ratio);
+    settings.samples_per_pixel = options
+        .samples_per_pixel
+        .unwrap_or(settings.samples_per_pixel);
+    settings.max_depth = options.max_depth.unwrap_or(settings.max_depth);
+    Ok(Command::Render(options))
+}
+
+impl Options {
+    /// Apply the settings of the command line to the camera of a
+    /// scene file.
+    fn camera(&self, mut camera: CameraBuilder) -> CameraBuilder {
+        if let Some(width) = self.width {
+            camera = camera.image_width(width);
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera = camera.aspect_ratio(aspect_ratio);
+        }
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            camera = camera.samples_per_pixel(samples_per_pixel);
+        }
+        camera
+            .seed(self.settings.seed)
+            .threads(self.settings.threads)
+    }
+
+    /// Apply the settings of the command line to the path tracer of a
+    /// scene file.
+    fn path_tracer(&self, path_tracer: PathTracer) -> PathTracer {
+        match self.max_depth {
+            Some(max_depth) => path_tracer.max_depth(max_depth),
+            None => path_tracer,
+        }
+    }
+}
+
+fn is_scene_file(scene: &str) -> bool {
+    scene.ends_with(".toml")
+}
+
+fn render(options: Options) -> Result<(), String> {
+    let path = std::path::Path::new(&options.scene);
+    let (name, builtin) = if is_scene_file(&options.scene) {
+        let stem = path.file_stem().and_then(|stem| stem.to_str());
+        (stem.unwrap_or("scene"), None)
+    } else {
+        let scene = scenes::find(&options.scene)
+            .ok_or_else(|| format!("Unknown scene: {:?} (try --list)", options.scene))?;
+        (scene.name, Some(scene))
+    };
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| format!("{name}.png"));
+
+    let format = match options.format {
+        Some(format) => format,
+        None if output == "-" => {
+            return Err(String::from("Writing to standard output requires --format"))
+        }
+        None => std::path::Path::new(&output)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ImageFormat::from_extension)
+            .ok_or_else(|| format!("Cannot guess the format of {output:?}, use --format"))?,
+    };
+
+    let image = match builtin {
+        Some(scene) => {
+            let mut settings = options.settings;
+            if let (None, Some(aspect_ratio)) = (options.aspect_ratio, scene.aspect_ratio) {
+                settings.aspect_ratio = aspect_ratio;
+            }
+            eprintln!("{}", scene.description);
+            eprintln!(
+                "Rendering {} on {}×{}, {} samples per pixel, {} threads",
+                scene.name,
+                settings.image_width,
+                ((settings.image_width as f64 / settings.aspect_ratio) as usize).max(1),
+                settings.samples_per_pixel,
+                settings.threads
+            );
+            scene.render(&settings)
+        }
+        None => {
+            let scene = SceneFile::load(path)
+                .map_err(|err| format!("Cannot load {}: {err}", options.scene))?;
+            let camera = options.camera(scene.camera).build();
+            eprintln!(
+                "Rendering {} on {}×{}, {} threads",
+                options.scene,
+                camera.image_width(),
+                camera.image_height(),
+                options.settings.threads
+            );
+            let integrator = options
+                .settings
+                .integrator(options.path_tracer(scene.path_tracer));
+            camera.render(&scene.world, &integrator)
+        }
+    };
+
+    let result = if output == "-" {
+        image.write_to(format, BufWriter::new(std::io::stdout().lock()))
+    } else {
+        std::fs::File::create(&output)
+            .map_err(Into::into)
+            .and_then(|file| image.write_to(format, BufWriter::new(file)))
+    };
+    result.map_err(|err| format!("Cannot write {output}: {err}"))
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Ok(Command::List) => {
+            for scene in SCENES {
+                println!("{:<16} {}", scene.name, scene.description);
+            }
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Render(options)) => match render(options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("raytrace: {err}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(err) => {
+            eprintln!("raytrace: {err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
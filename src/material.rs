@@ -0,0 +1,191 @@
+use crate::hittable::Hit;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::vec3::{Color, Vec3};
+use rand::prelude::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A material describes how light scatters off a surface.
+pub trait Material: Send + Sync {
+    /// Scatter `ray` off the surface at `hit`, drawing random numbers
+    /// from `rng`.  Returns the attenuation and the scattered ray, or
+    /// `None` if the ray is absorbed.
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Sampler) -> Option<(Color, Ray)>;
+
+    /// Probability density, over solid angle, of [`scatter`] sending
+    /// `ray` off in `direction`.  Zero for materials which scatter in
+    /// a few discrete directions, like mirrors and glass, and cannot
+    /// make use of light sampling.
+    ///
+    /// [`scatter`]: Material::scatter
+    fn scattering_pdf(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Fraction of the light arriving from `direction` which scatters
+    /// back along `ray`: the BSDF times the cosine of `direction` with
+    /// the normal.
+    fn eval(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> Color {
+        Color::zero()
+    }
+
+    /// Light emitted by the surface at `hit`, black for most materials.
+    fn emitted(&self, _hit: &Hit) -> Color {
+        Color::zero()
+    }
+}
+
+/// Ideal diffuse reflector.
+pub struct Lambertian {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Lambertian {
+        Lambertian::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// A diffuse reflector whose albedo varies over its surface.
+    pub fn textured(albedo: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { albedo }
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.albedo.value(hit.u, hit.v, hit.p)
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, _ray: &Ray, hit: &Hit, rng: &mut Sampler) -> Option<(Color, Ray)> {
+        let mut scatter_direction = hit.normal + Vec3::random_unit_vector(rng);
+
+        // Catch degenerate scatter direction
+        if scatter_direction.near_zero() {
+            scatter_direction = hit.normal;
+        }
+
+        Some((self.albedo(hit), Ray::new(hit.p, scatter_direction)))
+    }
+
+    /// Scattered directions follow the cosine with the normal.
+    fn scattering_pdf(&self, _ray: &Ray, hit: &Hit, direction: Vec3) -> f64 {
+        hit.normal.dot(direction.unit_vector()).max(0.0) / PI
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Color {
+        self.scattering_pdf(ray, hit, direction) * self.albedo(hit)
+    }
+}
+
+/// Reflective metal, optionally fuzzy.
+pub struct Metal {
+    albedo: Color,
+    fuzz: f64,
+}
+
+impl Metal {
+    /// Create a metal.  `fuzz` is the radius of the sphere the
+    /// reflected ray is perturbed within, clamped to 1; zero is a
+    /// perfect mirror.
+    pub fn new(albedo: Color, fuzz: f64) -> Metal {
+        Metal {
+            albedo,
+            fuzz: fuzz.min(1.0),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Sampler) -> Option<(Color, Ray)> {
+        let reflected = ray.direction.unit_vector().reflect(hit.normal);
+        let scattered = Ray::new(
+            hit.p,
+            reflected + self.fuzz * Vec3::random_in_unit_sphere(rng),
+        );
+        // Fuzzed rays pointing into the surface are absorbed.
+        if scattered.direction.dot(hit.normal) > 0.0 {
+            Some((self.albedo, scattered))
+        } else {
+            None
+        }
+    }
+}
+
+/// Clear refractive material, such as glass or water.
+pub struct Dielectric {
+    /// Refractive index in vacuum or air, or the ratio of the
+    /// material's refractive index over the refractive index of the
+    /// enclosing media
+    refraction_index: f64,
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Dielectric {
+        Dielectric { refraction_index }
+    }
+
+    /// Schlick's approximation for reflectance.
+    /// ```
+    /// use lib::material::Dielectric;
+    /// // At normal incidence, glass reflects about 4% of the light.
+    /// assert!((Dielectric::reflectance(1.0, 1.5) - 0.04).abs() < 1e-9);
+    /// // At grazing angles, everything is reflected.
+    /// assert!((Dielectric::reflectance(0.0, 1.5) - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+        let r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
+        let r0 = r0 * r0;
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Sampler) -> Option<(Color, Ray)> {
+        let ri = if hit.front_face {
+            1.0 / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+
+        let unit_direction = ray.direction.unit_vector();
+        let cos_theta = (-unit_direction).dot(hit.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = ri * sin_theta > 1.0;
+        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > rng.gen::<f64>() {
+            unit_direction.reflect(hit.normal)
+        } else {
+            unit_direction.refract(hit.normal, ri)
+        };
+
+        Some((Color::new(1.0, 1.0, 1.0), Ray::new(hit.p, direction)))
+    }
+}
+
+/// A surface that emits light evenly on both sides, and reflects
+/// none, like the panel of an area light.
+pub struct Emissive {
+    radiance: Color,
+}
+
+impl Emissive {
+    /// Emit `color` scaled by `strength`, which may take the radiance
+    /// well above 1 to light a scene.
+    pub fn new(color: Color, strength: f64) -> Emissive {
+        Emissive {
+            radiance: strength * color,
+        }
+    }
+}
+
+impl Material for Emissive {
+    fn scatter(&self, _ray: &Ray, _hit: &Hit, _rng: &mut Sampler) -> Option<(Color, Ray)> {
+        None
+    }
+
+    fn emitted(&self, _hit: &Hit) -> Color {
+        self.radiance
+    }
+}
@@ -11,6 +11,14 @@ pub struct Matrix<T> {
 }
 
 impl<T> Matrix<T> {
+    /// Build a Matrix from its row-major elements.
+    pub fn from_vec(width: usize, height: usize, vec: Vec<T>) -> Self {
+        if vec.len() != width * height {
+            panic!("Bad length: {} (dimensions are {width},{height})", vec.len());
+        }
+        Matrix { vec, width, height }
+    }
+
     pub fn to_index(&self, (x, y): (usize, usize)) -> usize {
         if !self.test_coords(x, y) {
             panic!("Bad coords: {x},{y} (dimensions are {},{})", self.width, self.height);
@@ -28,7 +36,7 @@ impl<T> Matrix<T> {
     }
 
     pub fn to_coords(&self, idx: usize) -> (usize, usize) {
-        if !idx < self.vec.len() {
+        if idx >= self.vec.len() {
             panic!("Out of bounds");
         }
         let x = idx % self.width;
@@ -0,0 +1,168 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::hittable::{Hit, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// A triangle of a mesh, as indices into the buffers of its
+/// [`MeshData`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Face {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    /// Index into [`MeshData::materials`]
+    pub material: usize,
+}
+
+/// Vertex buffers and faces of a triangle mesh.
+#[derive(Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f64; 2]>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<dyn Material>>,
+}
+
+/// A triangle mesh.  Triangles share the vertex buffers of the mesh,
+/// and are searched through a BVH of their own.
+pub struct Mesh {
+    triangles: Bvh<MeshTriangle>,
+}
+
+struct MeshTriangle {
+    data: Arc<MeshData>,
+    face: usize,
+}
+
+impl Mesh {
+    /// Build a mesh.
+    ///
+    /// # Panics
+    ///
+    /// If a face refers to a missing vertex or material.
+    pub fn new(data: MeshData) -> Mesh {
+        for face in &data.faces {
+            let valid = |indices: Option<[usize; 3]>, len: usize| {
+                indices.is_none_or(|indices| indices.iter().all(|&i| i < len))
+            };
+            assert!(
+                valid(Some(face.positions), data.positions.len())
+                    && valid(face.normals, data.normals.len())
+                    && valid(face.uvs, data.uvs.len())
+                    && face.material < data.materials.len(),
+                "Invalid mesh face {face:?}"
+            );
+        }
+
+        let data = Arc::new(data);
+        let triangles = (0..data.faces.len())
+            .map(|face| MeshTriangle {
+                data: data.clone(),
+                face,
+            })
+            .collect();
+        Mesh {
+            triangles: Bvh::new(triangles),
+        }
+    }
+
+    /// Number of triangles
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        self.triangles.hit(ray, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangles.bounding_box()
+    }
+}
+
+impl MeshTriangle {
+    fn face(&self) -> &Face {
+        &self.data.faces[self.face]
+    }
+
+    fn positions(&self) -> [Point3; 3] {
+        self.face().positions.map(|i| self.data.positions[i])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let positions = self.positions();
+        let (t, b1, b2) = triangle::intersect(ray, ray_t, &positions)?;
+        let face = self.face();
+        Some(triangle::shade(
+            ray,
+            t,
+            [b1, b2],
+            &positions,
+            face.normals
+                .map(|n| n.map(|i| self.data.normals[i]))
+                .as_ref(),
+            face.uvs.map(|uv| uv.map(|i| self.data.uvs[i])).as_ref(),
+            self.data.materials[face.material].as_ref(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        triangle::bounding_box(&self.positions())
+    }
+}
+
+#[test]
+fn mesh_test() {
+    use crate::{Color, Lambertian, Metal};
+
+    // A unit square in the z=-1 plane, made of two triangles with
+    // different materials.
+    let matte: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let shiny: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.5, 0.5, 0.5), 0.0));
+    let face = |positions, material| Face {
+        positions,
+        normals: None,
+        uvs: Some(positions),
+        material,
+    };
+    let mesh = Mesh::new(MeshData {
+        positions: vec![
+            Point3::new(0.0, 0.0, -1.0),
+            Point3::new(1.0, 0.0, -1.0),
+            Point3::new(1.0, 1.0, -1.0),
+            Point3::new(0.0, 1.0, -1.0),
+        ],
+        normals: vec![],
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+        faces: vec![face([0, 1, 2], 0), face([0, 2, 3], 1)],
+        materials: vec![matte.clone(), shiny.clone()],
+    });
+    assert_eq!(mesh.len(), 2);
+
+    let hit_at = |x, y| {
+        let ray = Ray::new(Point3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        mesh.hit(&ray, Interval::positive_or_null())
+    };
+    let same = |a: &dyn Material, b: &Arc<dyn Material>| std::ptr::addr_eq(a, b.as_ref());
+
+    let hit = hit_at(0.75, 0.25).unwrap();
+    assert!(same(hit.material, &matte));
+    assert!((hit.u - 0.75).abs() < 1e-12 && (hit.v - 0.25).abs() < 1e-12);
+    let hit = hit_at(0.25, 0.75).unwrap();
+    assert!(same(hit.material, &shiny));
+    assert!(hit_at(1.5, 0.5).is_none());
+}
@@ -0,0 +1,419 @@
+//! Wavefront OBJ and MTL import.
+//!
+//! Vertices, texture coordinates, normals and polygonal faces are
+//! read, polygons being split into triangle fans; groups, smoothing
+//! groups, lines and other statements are ignored.  Faces use the
+//! material of the last `usemtl`, or a grey diffuse one when there is
+//! none or it is unknown.
+//!
+//! MTL materials are mapped onto the renderer's materials:
+//! - those with an emission `Ke` other than black become [`Emissive`];
+//! - transparent ones, with a dissolve `d` below 1, a transparency
+//!   `Tr` above 0 or a refracting `illum` model (4, 6, 7 or 9), become
+//!   [`Dielectric`], with refraction index `Ni` (1.5 when missing);
+//! - those mostly specular, with a `Ks` brighter than their `Kd` or
+//!   with `illum 3`, become [`Metal`] of albedo `Ks`, rougher as the
+//!   `Ns` exponent is lower;
+//! - the others become [`Lambertian`] of albedo `Kd`.
+
+use crate::material::{Dielectric, Emissive, Lambertian, Material, Metal};
+use crate::mesh::{Face, Mesh, MeshData};
+use crate::vec3::{Color, Vec3};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        file: PathBuf,
+        error: std::io::Error,
+    },
+    /// Invalid statement, at a 1-based line of `file`.
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { file, error } => write!(f, "{}: {error}", file.display()),
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}, line {line}: {message}", file.display()),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Load an OBJ file, and the MTL files it refers to, as a mesh.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh, ObjError> {
+    let path = path.as_ref();
+    parse(&read(path)?, path)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|error| ObjError::Io {
+        file: path.to_owned(),
+        error,
+    })
+}
+
+/// One `v/vt/vn` vertex of a face, as 0-based indices.
+struct Vertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// One statement of an OBJ or MTL file, split into whitespace
+/// separated arguments.
+struct Statement<'a> {
+    file: &'a Path,
+    line: usize,
+    args: SplitWhitespace<'a>,
+}
+
+impl Statement<'_> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            file: self.file.to_owned(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn parse<T: FromStr>(&self, arg: Option<&str>) -> Result<T, ObjError> {
+        let arg = arg.ok_or_else(|| self.error(String::from("missing argument")))?;
+        arg.parse()
+            .map_err(|_| self.error(format!("invalid argument `{arg}`")))
+    }
+
+    fn number(&mut self) -> Result<f64, ObjError> {
+        let arg = self.args.next();
+        self.parse(arg)
+    }
+
+    /// A number which may be omitted, like the `v` of a `vt`.
+    fn optional_number(&mut self, default: f64) -> Result<f64, ObjError> {
+        match self.args.next() {
+            None => Ok(default),
+            arg => self.parse(arg),
+        }
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, ObjError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    /// The rest of the line, for names which may contain spaces.
+    fn name(&mut self) -> Result<String, ObjError> {
+        let name = self.args.by_ref().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            Err(self.error(String::from("missing name")))
+        } else {
+            Ok(name)
+        }
+    }
+
+    /// Resolve a 1-based or negative (relative to the end) index into
+    /// a buffer of `len` elements.
+    fn index(&self, arg: &str, len: usize) -> Result<usize, ObjError> {
+        let index: i64 = self.parse(Some(arg))?;
+        let resolved = match index {
+            1.. => index - 1,
+            ..=-1 => len as i64 + index,
+            0 => -1,
+        };
+        if (0..len as i64).contains(&resolved) {
+            Ok(resolved as usize)
+        } else {
+            Err(self.error(format!("index {index} out of range")))
+        }
+    }
+
+    fn vertex(&self, arg: &str, data: &MeshData) -> Result<Vertex, ObjError> {
+        let mut indices = arg.split('/');
+        let position = self.index(indices.next().unwrap_or(""), data.positions.len())?;
+        let mut optional = |len| match indices.next() {
+            None | Some("") => Ok(None),
+            Some(index) => self.index(index, len).map(Some),
+        };
+        Ok(Vertex {
+            position,
+            uv: optional(data.uvs.len())?,
+            normal: optional(data.normals.len())?,
+        })
+    }
+}
+
+/// Iterate over the statements of `text`, skipping comments and
+/// blank lines.
+fn statements<'a>(
+    text: &'a str,
+    file: &'a Path,
+) -> impl Iterator<Item = (&'a str, Statement<'a>)> + 'a {
+    text.lines().enumerate().filter_map(move |(i, line)| {
+        let mut args = line.split('#').next().unwrap_or("").split_whitespace();
+        let keyword = args.next()?;
+        Some((
+            keyword,
+            Statement {
+                file,
+                line: i + 1,
+                args,
+            },
+        ))
+    })
+}
+
+fn parse(text: &str, path: &Path) -> Result<Mesh, ObjError> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut data = MeshData {
+        materials: vec![Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))],
+        ..MeshData::default()
+    };
+    let mut material_names: HashMap<String, usize> = HashMap::new();
+    let mut material = 0;
+
+    for (keyword, mut statement) in statements(text, path) {
+        match keyword {
+            "v" => data.positions.push(statement.vec3()?),
+            "vn" => data.normals.push(statement.vec3()?),
+            "vt" => {
+                let u = statement.number()?;
+                let v = statement.optional_number(0.0)?;
+                data.uvs.push([u, v]);
+            }
+            "f" => {
+                let vertices = statement
+                    .args
+                    .clone()
+                    .map(|arg| statement.vertex(arg, &data))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if vertices.len() < 3 {
+                    return Err(statement.error(String::from("face with less than 3 vertices")));
+                }
+                for i in 1..vertices.len() - 1 {
+                    let triangle = [&vertices[0], &vertices[i], &vertices[i + 1]];
+                    let all = |index: fn(&Vertex) -> Option<usize>| {
+                        let [a, b, c] = triangle.map(index);
+                        Some([a?, b?, c?])
+                    };
+                    data.faces.push(Face {
+                        positions: triangle.map(|v| v.position),
+                        normals: all(|v| v.normal),
+                        uvs: all(|v| v.uv),
+                        material,
+                    });
+                }
+            }
+            "usemtl" => {
+                let name = statement.name()?;
+                material = material_names.get(&name).copied().unwrap_or(0);
+            }
+            "mtllib" => {
+                // File names may not contain spaces here, as several
+                // libraries can be listed.
+                for name in statement.args.clone() {
+                    let mtl_path = dir.join(name);
+                    for (name, mtl) in parse_mtl(&read(&mtl_path)?, &mtl_path)? {
+                        material_names.insert(name, data.materials.len());
+                        data.materials.push(mtl.material());
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Mesh::new(data))
+}
+
+/// The values of an MTL material which matter to us.
+#[derive(Debug, PartialEq)]
+struct MtlMaterial {
+    diffuse: Color,
+    specular: Color,
+    emission: Color,
+    exponent: f64,
+    refraction_index: Option<f64>,
+    dissolve: f64,
+    illum: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: Color::new(0.5, 0.5, 0.5),
+            specular: Color::zero(),
+            emission: Color::zero(),
+            exponent: 0.0,
+            refraction_index: None,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn material(&self) -> Arc<dyn Material> {
+        let max = |c: Color| c.x.max(c.y).max(c.z);
+        if max(self.emission) > 0.0 {
+            Arc::new(Emissive::new(self.emission, 1.0))
+        } else if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Arc::new(Dielectric::new(self.refraction_index.unwrap_or(1.5)))
+        } else if max(self.specular) > max(self.diffuse) || self.illum == 3 {
+            // Map the Phong exponent onto a roughness, sharp above a
+            // few hundreds.
+            let fuzz = (2.0 / (self.exponent.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(self.specular, fuzz))
+        } else {
+            Arc::new(Lambertian::new(self.diffuse))
+        }
+    }
+}
+
+fn parse_mtl(text: &str, path: &Path) -> Result<Vec<(String, MtlMaterial)>, ObjError> {
+    let mut materials: Vec<(String, MtlMaterial)> = Vec::new();
+
+    for (keyword, mut statement) in statements(text, path) {
+        if keyword == "newmtl" {
+            materials.push((statement.name()?, MtlMaterial::default()));
+            continue;
+        }
+        let Some((_, mtl)) = materials.last_mut() else {
+            // Statements before the first material have nothing to
+            // apply to.
+            continue;
+        };
+        match keyword {
+            "Kd" => mtl.diffuse = statement.vec3()?,
+            "Ks" => mtl.specular = statement.vec3()?,
+            "Ke" => mtl.emission = statement.vec3()?,
+            "Ns" => mtl.exponent = statement.number()?,
+            "Ni" => mtl.refraction_index = Some(statement.number()?),
+            "d" => mtl.dissolve = statement.number()?,
+            "Tr" => mtl.dissolve = 1.0 - statement.number()?,
+            "illum" => {
+                let arg = statement.args.next();
+                mtl.illum = statement.parse(arg)?;
+            }
+            _ => (),
+        }
+    }
+    Ok(materials)
+}
+
+#[test]
+fn obj_test() {
+    use crate::{Hittable, Interval, Point3, Ray};
+
+    let path = Path::new("quad.obj");
+    let mesh = parse(
+        "# A unit quad, facing +z\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+         vn 0 0 1\n\
+         o quad\ns off\n\
+         f 1/1/1 2/2/1 3/3/1 -1/-1/-1\n",
+        path,
+    )
+    .unwrap();
+    assert_eq!(mesh.len(), 2);
+    let ray = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let hit = mesh.hit(&ray, Interval::positive_or_null()).unwrap();
+    assert!((hit.u - 0.25).abs() < 1e-12 && (hit.v - 0.75).abs() < 1e-12);
+    assert!(hit.normal == Vec3::new(0.0, 0.0, 1.0));
+
+    let error = |text| match parse(text, path) {
+        Err(ObjError::Parse { line, message, .. }) => (line, message),
+        _ => panic!("{text:?} should not parse"),
+    };
+    assert_eq!(
+        error("v 0 0 0\nv 1 0\n"),
+        (2, String::from("missing argument"))
+    );
+    assert_eq!(
+        error("v 0 0 zero\n"),
+        (1, String::from("invalid argument `zero`"))
+    );
+    assert_eq!(
+        error("v 0 0 0\nf 1 2 3\n"),
+        (2, String::from("index 2 out of range"))
+    );
+    assert_eq!(
+        error("v 0 0 0\n\nf 1 1\n"),
+        (3, String::from("face with less than 3 vertices"))
+    );
+}
+
+#[test]
+fn mtl_test() {
+    use crate::sampler::{pixel_sampler, Sampler};
+    use crate::{Hittable, Interval, Point3, Ray, Sphere, Vec3};
+
+    let materials = parse_mtl(
+        "newmtl clay\nKd 0.8 0.4 0.2\nKs 0.5 0.5 0.5\nillum 2\n\
+         newmtl chrome\nKd 0 0 0\nKs 0.9 0.9 0.9\nNs 1000\n\
+         newmtl glass\nd 0.1\nNi 1.33\n\
+         newmtl lamp\nKe 4 4 3\n",
+        Path::new("test.mtl"),
+    )
+    .unwrap();
+    let names: Vec<&str> = materials.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["clay", "chrome", "glass", "lamp"]);
+    assert!(materials[0].1.diffuse == Color::new(0.8, 0.4, 0.2));
+    assert!(materials[1].1.exponent == 1000.0);
+    assert!(materials[2].1.dissolve == 0.1);
+    assert!(materials[2].1.refraction_index == Some(1.33));
+    assert!(materials[3].1.emission == Color::new(4.0, 4.0, 3.0));
+
+    // The mapping onto materials, told apart by how they scatter a ray
+    // hitting a sphere head on
+    let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+    let mut rng = pixel_sampler(0, 0);
+    let scatter = |name: &str, rng: &mut Sampler| {
+        let (_, mtl) = materials.iter().find(|(n, _)| n == name).unwrap();
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 1.0, mtl.material());
+        let hit = sphere.hit(&ray, Interval::positive_or_null()).unwrap();
+        let diffuse = hit.material.scattering_pdf(&ray, &hit, hit.normal);
+        let emitted = hit.material.emitted(&hit);
+        (hit.material.scatter(&ray, &hit, rng), diffuse, emitted)
+    };
+
+    // Clay is diffuse, with its Kd as albedo.
+    let (scattered, diffuse, emitted) = scatter("clay", &mut rng);
+    assert!((diffuse - 1.0 / std::f64::consts::PI).abs() < 1e-9);
+    assert!(scattered.unwrap().0 == Color::new(0.8, 0.4, 0.2));
+    assert!(emitted == Color::zero());
+
+    // Chrome is a nearly sharp mirror of its Ks.
+    let (scattered, diffuse, _) = scatter("chrome", &mut rng);
+    let (attenuation, reflected) = scattered.unwrap();
+    assert!(diffuse == 0.0 && attenuation == Color::new(0.9, 0.9, 0.9));
+    assert!(reflected.direction.unit_vector().z > 0.99);
+
+    // Glass refracts or reflects, without absorbing.
+    let (scattered, diffuse, _) = scatter("glass", &mut rng);
+    assert!(diffuse == 0.0 && scattered.unwrap().0 == Color::new(1.0, 1.0, 1.0));
+
+    // The lamp emits its Ke, and scatters nothing.
+    let (scattered, _, emitted) = scatter("lamp", &mut rng);
+    assert!(scattered.is_none() && emitted == Color::new(4.0, 4.0, 3.0));
+}
@@ -0,0 +1,36 @@
+use crate::vec3::Vec3;
+
+/// An orthonormal basis, built around a direction `w`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// A basis whose `w` axis is along `n`, which need not be of unit
+    /// length.
+    /// ```
+    /// use lib::{Onb, Vec3};
+    /// let onb = Onb::new(Vec3::new(0.0, 0.0, 2.0));
+    /// assert!(onb.w == Vec3::new(0.0, 0.0, 1.0));
+    /// assert!(onb.u.dot(onb.v).abs() < 1e-12 && onb.u.cross(onb.v) == onb.w);
+    /// ```
+    pub fn new(n: Vec3) -> Onb {
+        let w = n.unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).unit_vector();
+        let u = v.cross(w);
+        Onb { u, v, w }
+    }
+
+    /// Convert `a`, given in this basis, to world coordinates.
+    pub fn transform(&self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
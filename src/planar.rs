@@ -0,0 +1,341 @@
+//! Flat primitives: infinite planes, quadrilaterals and disks, and
+//! boxes made of quadrilaterals.  See also [`Triangle`](crate::Triangle).
+
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Thickness given to the bounding boxes of flat objects.
+const PADDING: f64 = 1e-4;
+
+/// Distance along `ray` to the plane of unit `normal` through
+/// `point`, if within `ray_t`.
+fn hit_plane(ray: &Ray, ray_t: Interval, point: Point3, normal: Vec3) -> Option<f64> {
+    let denom = normal.dot(ray.direction);
+    // The ray is parallel to the plane.
+    if denom.abs() < 1e-12 {
+        return None;
+    }
+    let t = (point - ray.origin).dot(normal) / denom;
+    ray_t.surrounds(t).then_some(t)
+}
+
+/// An infinite plane.  Surface coordinates are distances from `point`
+/// along two directions of the plane.
+pub struct Plane {
+    point: Point3,
+    basis: Onb,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Plane {
+        Plane {
+            point,
+            basis: Onb::new(normal),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let t = hit_plane(ray, ray_t, self.point, self.basis.w)?;
+        let offset = ray.at(t) - self.point;
+        Some(Hit::new(
+            ray,
+            t,
+            self.basis.w,
+            self.material.as_ref(),
+            offset.dot(self.basis.u),
+            offset.dot(self.basis.v),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::universe()
+    }
+}
+
+/// A parallelogram with a corner at `q` and sides `u` and `v`.  Its
+/// front face is the side `u × v` points to, and its surface
+/// coordinates go from 0 to 1 along `u` and `v`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    /// `n / (n · n)` with `n = u × v`, to find the coordinates of
+    /// points along `u` and `v`
+    w: Vec3,
+    area: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Quad {
+        let n = u.cross(v);
+        Quad {
+            q,
+            u,
+            v,
+            normal: n.unit_vector(),
+            w: n / n.dot(n),
+            area: n.length(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let t = hit_plane(ray, ray_t, self.q, self.normal)?;
+
+        let planar = ray.at(t) - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        let unit = Interval::new(0.0, 1.0);
+        if !unit.contains(alpha) || !unit.contains(beta) {
+            return None;
+        }
+
+        Some(Hit::new(
+            ray,
+            t,
+            self.normal,
+            self.material.as_ref(),
+            alpha,
+            beta,
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.q, self.q + self.u + self.v)
+            .union(Aabb::from_points(self.q + self.u, self.q + self.v))
+            .pad_to_minimums(PADDING)
+    }
+
+    /// Points are sampled uniformly over the area of the quad.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let ray = Ray::new(origin, direction);
+        let Some(hit) = self.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+            return 0.0;
+        };
+        let distance_squared = hit.t * hit.t * direction.length_squared();
+        let cosine = direction.dot(self.normal).abs() / direction.length();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        Some(self.q + rng.gen::<f64>() * self.u + rng.gen::<f64>() * self.v - origin)
+    }
+}
+
+/// A disk.  Its surface coordinates are the distance to the center,
+/// as a fraction of the radius, and the angle around the normal, as
+/// a fraction of a turn.
+pub struct Disk {
+    center: Point3,
+    radius: f64,
+    basis: Onb,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<dyn Material>) -> Disk {
+        Disk {
+            center,
+            radius,
+            basis: Onb::new(normal),
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let t = hit_plane(ray, ray_t, self.center, self.basis.w)?;
+        let offset = ray.at(t) - self.center;
+        let distance = offset.length();
+        if distance > self.radius {
+            return None;
+        }
+
+        let angle = offset.dot(self.basis.v).atan2(offset.dot(self.basis.u));
+        Some(Hit::new(
+            ray,
+            t,
+            self.basis.w,
+            self.material.as_ref(),
+            distance / self.radius,
+            angle.rem_euclid(2.0 * std::f64::consts::PI) / (2.0 * std::f64::consts::PI),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Along each axis, the disk spreads over the radius times the
+        // sine of the angle between the axis and the normal.
+        let n = self.basis.w;
+        let extent = |c: f64| self.radius * (1.0 - c * c).max(0.0).sqrt();
+        let e = Vec3::new(extent(n.x), extent(n.y), extent(n.z));
+        Aabb::from_points(self.center - e, self.center + e).pad_to_minimums(PADDING)
+    }
+}
+
+/// An axis-aligned box, made of six quads facing outwards.
+pub struct BoxShape {
+    sides: [Quad; 6],
+    bbox: Aabb,
+}
+
+impl BoxShape {
+    /// The box with opposite corners `a` and `b`.
+    pub fn new(a: Point3, b: Point3, material: Arc<dyn Material>) -> BoxShape {
+        let min = Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+        let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+        let side = |q, u, v| Quad::new(q, u, v, material.clone());
+
+        BoxShape {
+            sides: [
+                side(Point3::new(min.x, min.y, max.z), dx, dy), // front
+                side(Point3::new(max.x, min.y, max.z), -dz, dy), // right
+                side(Point3::new(max.x, min.y, min.z), -dx, dy), // back
+                side(Point3::new(min.x, min.y, min.z), dz, dy), // left
+                side(Point3::new(min.x, max.y, max.z), dx, -dz), // top
+                side(Point3::new(min.x, min.y, min.z), dx, dz), // bottom
+            ],
+            bbox: Aabb::from_points(min, max).pad_to_minimums(PADDING),
+        }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let mut closest = ray_t.max;
+        let mut ret = None;
+        for side in &self.sides {
+            if let Some(hit) = side.hit(ray, Interval::new(ray_t.min, closest)) {
+                closest = hit.t;
+                ret = Some(hit);
+            }
+        }
+        ret
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[test]
+fn planar_test() {
+    use crate::{Color, Lambertian};
+
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let down = |x, z| Ray::new(Point3::new(x, 2.0, z), Vec3::new(0.0, -1.0, 0.0));
+    let up = |x, z| Ray::new(Point3::new(x, -2.0, z), Vec3::new(0.0, 1.0, 0.0));
+    fn hits(object: &dyn Hittable, ray: Ray) -> Option<Hit<'_>> {
+        object.hit(&ray, Interval::positive_or_null())
+    }
+
+    let plane = Plane::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), material.clone());
+    let hit = hits(&plane, down(1e6, -3.0)).unwrap();
+    assert!(hit.t == 2.0 && hit.front_face && hit.normal == Vec3::new(0.0, 1.0, 0.0));
+    let hit = hits(&plane, up(0.0, 0.0)).unwrap();
+    assert!(!hit.front_face && hit.normal == Vec3::new(0.0, -1.0, 0.0));
+    let parallel = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+    assert!(hits(&plane, parallel).is_none());
+    assert!(!plane.bounding_box().is_bounded());
+
+    // A 2×4 quad facing up
+    let quad = Quad::new(
+        Point3::new(-1.0, 0.0, 2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        material.clone(),
+    );
+    let hit = hits(&quad, down(0.5, 1.0)).unwrap();
+    assert!(hit.t == 2.0 && hit.front_face);
+    assert!(hit.u == 0.75 && hit.v == 0.25);
+    assert!(hits(&quad, down(1.5, 0.0)).is_none());
+    assert!(hits(&quad, down(0.0, -2.5)).is_none());
+    let bbox = quad.bounding_box();
+    assert!(bbox.x == Interval::new(-1.0, 1.0) && bbox.z == Interval::new(-2.0, 2.0));
+    assert!(bbox.y.size() > 0.0);
+
+    let disk = Disk::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), 2.0, material);
+    let hit = hits(&disk, down(1.0, 0.0)).unwrap();
+    assert!(hit.t == 2.0 && hit.u == 0.5);
+    assert!(hits(&disk, down(1.5, 1.5)).is_none());
+    let bbox = disk.bounding_box();
+    assert!(bbox.x == Interval::new(-2.0, 2.0) && bbox.z == Interval::new(-2.0, 2.0));
+}
+
+#[test]
+fn quad_light_test() {
+    use crate::sampler::pixel_sampler;
+    use crate::{Color, Lambertian};
+
+    // A 2×2 square seen from 1 unit above its center fills a solid
+    // angle of 2π/3, which sampling must recover.
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let quad = Quad::new(
+        Point3::new(-1.0, -1.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        material,
+    );
+    let origin = Point3::new(0.0, 0.0, 1.0);
+    let mut rng = pixel_sampler(0, 0);
+    let n = 10000;
+    let solid_angle: f64 = (0..n)
+        .map(|_| 1.0 / quad.pdf_value(origin, quad.random(origin, &mut rng).unwrap()))
+        .sum::<f64>()
+        / n as f64;
+    assert!((solid_angle - 2.0 * std::f64::consts::PI / 3.0).abs() < 0.02);
+    assert!(quad.pdf_value(origin, Vec3::new(0.0, 0.0, 1.0)) == 0.0);
+}
+
+#[test]
+fn box_test() {
+    use crate::{Color, Lambertian};
+
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let cube = BoxShape::new(
+        Point3::new(1.0, 1.0, 1.0),
+        Point3::new(-1.0, -1.0, -1.0),
+        material,
+    );
+
+    // Every side is hit from outside on its front face, and from
+    // inside on its back face.
+    for axis in 0..3 {
+        for sign in [-1.0, 1.0] {
+            let mut direction = Vec3::zero();
+            match axis {
+                0 => direction.x = sign,
+                1 => direction.y = sign,
+                _ => direction.z = sign,
+            }
+            let outside = Ray::new(Point3::new(0.1, 0.2, 0.3) - 5.0 * direction, direction);
+            let hit = cube.hit(&outside, Interval::positive_or_null()).unwrap();
+            assert!(hit.front_face && hit.normal == -direction);
+            assert!((hit.t - 4.0 + sign * [0.1, 0.2, 0.3][axis]).abs() < 1e-12);
+
+            let inside = Ray::new(Point3::zero(), direction);
+            let hit = cube.hit(&inside, Interval::positive_or_null()).unwrap();
+            assert!(!hit.front_face && hit.t == 1.0);
+        }
+    }
+}
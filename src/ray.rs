@@ -1,5 +1,6 @@
 use crate::vec3::{Point3,Vec3};
 
+#[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
@@ -0,0 +1,40 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+/// The random number generator threaded through the renderer.  Every
+/// random draw made while rendering goes through one of these, so a
+/// render is fully determined by its seed.  ChaCha8 is named rather
+/// than `StdRng`, whose algorithm may change between versions of
+/// `rand`, so that a seed gives the same image across versions.
+pub type Sampler = ChaCha8Rng;
+
+/// Build the sampler for pixel `index` of a render seeded with
+/// `seed`.  Each pixel gets its own independent stream, so the result
+/// does not depend on which thread renders which pixel, or in which
+/// order.
+/// ```
+/// use lib::sampler::pixel_sampler;
+/// use rand::Rng;
+/// let a: f64 = pixel_sampler(42, 7).gen();
+/// let b: f64 = pixel_sampler(42, 7).gen();
+/// let c: f64 = pixel_sampler(42, 8).gen();
+/// assert_eq!(a, b);
+/// assert_ne!(a, c);
+/// ```
+pub fn pixel_sampler(seed: u64, index: usize) -> Sampler {
+    // Spread consecutive pixel indices over the seed space
+    // (SplitMix64 finalizer), so neighbouring pixels do not get
+    // related seeds.
+    let mut z = seed.wrapping_add((index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    Sampler::seed_from_u64(z ^ (z >> 31))
+}
+
+#[test]
+fn sampler_stream_test() {
+    // The streams are pinned: changing them changes every render.
+    let mut rng = pixel_sampler(0, 0);
+    assert_eq!(rng.gen::<u64>(), 0xb585_f767_a79a_3b6c);
+    assert_eq!(rng.gen::<u64>(), 0x7746_a55f_bad8_c037);
+}
@@ -0,0 +1,869 @@
+//! Declarative scene descriptions, written in TOML.
+//!
+//! A scene file holds an optional `[camera]` table, an optional
+//! `[background]` table, named `[materials.NAME]` tables and arrays of
+//! shapes, like `[[spheres]]`, referring to those materials by name:
+//!
+//! ```toml
+//! [camera]
+//! width = 400
+//! aspect_ratio = 1.5
+//! samples_per_pixel = 100
+//! max_depth = 50
+//! vfov = 20
+//! lookfrom = [13, 2, 3]
+//! lookat = [0, 0, 0]
+//! defocus_angle = 0.6
+//! focus_dist = 10
+//!
+//! [background]
+//! type = "gradient"
+//! bottom = [1, 1, 1]
+//! top = [0.5, 0.7, 1]
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [materials.glass]
+//! type = "dielectric"
+//! refraction_index = 1.5
+//!
+//! [[spheres]]
+//! center = [0, -1000, 0]
+//! radius = 1000
+//! material = "ground"
+//!
+//! [[spheres]]
+//! center = [0, 1, 0]
+//! radius = 1
+//! material = "glass"
+//! ```
+//!
+//! The tables are:
+//! - `[camera]`: every key is optional, and defaults to the
+//!   [`CameraBuilder`] default.  `max_depth` configures the
+//!   [`path_tracer`](SceneFile::path_tracer).
+//! - `[materials.NAME]`: `lambertian` (`albedo`), `metal` (`albedo`,
+//!   `fuzz`), `dielectric` (`refraction_index`) and `emissive`
+//!   (`color`, `strength` defaulting to 1).  Emissive spheres and quads
+//!   are sampled as lights by the path tracer.
+//! - `[background]`: `gradient` (`bottom`, `top`), `solid` (`color`),
+//!   `none`, for scenes lit only by their emissive objects, `image`
+//!   and `sky`.
+//! - Shapes: besides `[[spheres]]`, `[[planes]]`, infinite, through a
+//!   `point` with a `normal`; `[[quads]]`, with a `corner` and two
+//!   sides `u` and `v`; `[[disks]]`, with a `center`, a `normal` and a
+//!   `radius`; `[[boxes]]`, axis-aligned, with opposite corners `from`
+//!   and `to`; `[[triangles]]`, with three `vertices`.  All have a
+//!   `material`.
+//! - `[[meshes]]`: the `file` of a Wavefront OBJ mesh using its own MTL
+//!   materials, relative to the scene file.  A mesh file used several
+//!   times is loaded once, and shared by its instances.
+//!
+//! Transforms: boxes and meshes may be scaled by a `scale` factor along
+//! each axis, then rotated by `rotate` degrees around the X, Y then Z
+//! axes, then moved by `translate`.
+//!
+//! Image backgrounds are equirectangular panoramas (`file`, relative to
+//! the scene file, turned by `rotation` degrees and scaled by
+//! `intensity`; see [`EnvironmentMap`]).
+//!
+//! Sky backgrounds are a clear daylight sky with the sun `elevation`
+//! degrees above the horizon and `azimuth` degrees clockwise from -Z,
+//! and optionally a `turbidity`, a `ground_albedo` and an `intensity`
+//! (see [`Sky`]).
+
+use crate::bvh::Bvh;
+use crate::camera::{Camera, CameraBuilder};
+use crate::environment::{Background, EnvironmentMap, Sky};
+use crate::hittable::{Hittable, HittableList, Sphere};
+use crate::image::Image;
+use crate::integrator::PathTracer;
+use crate::material::{Dielectric, Emissive, Lambertian, Material, Metal};
+use crate::mesh::Mesh;
+use crate::obj;
+use crate::planar::{BoxShape, Disk, Plane, Quad};
+use crate::transform::{Transform, Transformed};
+use crate::triangle::Triangle;
+use crate::vec3::Vec3;
+use serde::{Deserialize, Deserializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+
+/// A scene loaded from a scene file: a configured camera, the world
+/// to render with it, and a path tracer set up with the background
+/// and the lights of the scene.
+pub struct SceneFile {
+    pub camera: CameraBuilder,
+    pub world: Bvh<Box<dyn Hittable>>,
+    pub path_tracer: PathTracer,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    /// Invalid scene file, at a 1-based line and column.
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl SceneError {
+    /// An error about the bytes of `text` at `span`.
+    fn at(text: &str, span: Range<usize>, message: String) -> SceneError {
+        let before = &text[..span.start.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        SceneError::Parse {
+            line,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Description {
+    #[serde(default)]
+    camera: CameraDescription,
+    background: Option<Spanned<BackgroundDescription>>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    spheres: Vec<SphereDescription>,
+    #[serde(default)]
+    planes: Vec<PlaneDescription>,
+    #[serde(default)]
+    quads: Vec<QuadDescription>,
+    #[serde(default)]
+    disks: Vec<DiskDescription>,
+    #[serde(default)]
+    boxes: Vec<BoxDescription>,
+    #[serde(default)]
+    triangles: Vec<TriangleDescription>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    #[serde(default, deserialize_with = "positive_count")]
+    width: Option<usize>,
+    #[serde(default, deserialize_with = "positive")]
+    aspect_ratio: Option<f64>,
+    #[serde(default, deserialize_with = "positive_count")]
+    samples_per_pixel: Option<usize>,
+    #[serde(default, deserialize_with = "positive_count")]
+    max_depth: Option<usize>,
+    #[serde(default, deserialize_with = "field_of_view")]
+    vfov: Option<f64>,
+    lookfrom: Option<Spanned<[f64; 3]>>,
+    lookat: Option<Spanned<[f64; 3]>>,
+    vup: Option<Spanned<[f64; 3]>>,
+    defocus_angle: Option<f64>,
+    #[serde(default, deserialize_with = "positive")]
+    focus_dist: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDescription {
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+    Solid { color: [f64; 3] },
+    None,
+    Image {
+        file: String,
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "one")]
+        intensity: f64,
+    },
+    Sky {
+        elevation: f64,
+        #[serde(default)]
+        azimuth: f64,
+        turbidity: Option<f64>,
+        ground_albedo: Option<[f64; 3]>,
+        intensity: Option<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        albedo: [f64; 3],
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        #[serde(deserialize_with = "positive")]
+        refraction_index: f64,
+    },
+    Emissive {
+        color: [f64; 3],
+        #[serde(default = "one")]
+        strength: f64,
+    },
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f64; 3],
+    #[serde(deserialize_with = "positive")]
+    radius: f64,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    point: [f64; 3],
+    #[serde(deserialize_with = "nonzero")]
+    normal: [f64; 3],
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuadDescription {
+    corner: [f64; 3],
+    u: Spanned<[f64; 3]>,
+    v: Spanned<[f64; 3]>,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiskDescription {
+    center: [f64; 3],
+    #[serde(deserialize_with = "nonzero")]
+    normal: [f64; 3],
+    #[serde(deserialize_with = "positive")]
+    radius: f64,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoxDescription {
+    from: [f64; 3],
+    to: [f64; 3],
+    material: Spanned<String>,
+    #[serde(default, deserialize_with = "scale_factors")]
+    scale: Option<[f64; 3]>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDescription {
+    vertices: [[f64; 3]; 3],
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    file: Spanned<String>,
+    #[serde(default, deserialize_with = "scale_factors")]
+    scale: Option<[f64; 3]>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+}
+
+/// Deserialize a strictly positive number, or an optional one.
+fn positive<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Positive,
+{
+    let value = T::deserialize(deserializer)?;
+    if value.is_positive() {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom("expected a positive number"))
+    }
+}
+
+fn positive_count<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    positive(deserializer)
+}
+
+/// Deserialize a nonzero vector, like a normal.
+fn nonzero<'de, D>(deserializer: D) -> Result<[f64; 3], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = <[f64; 3]>::deserialize(deserializer)?;
+    if value != [0.0; 3] {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom("expected a nonzero vector"))
+    }
+}
+
+/// Deserialize optional scale factors, none of which may be zero.
+fn scale_factors<'de, D>(deserializer: D) -> Result<Option<[f64; 3]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<[f64; 3]>::deserialize(deserializer)?;
+    if value.is_none_or(|factors| factors.iter().all(|&f| f != 0.0)) {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom("expected nonzero scale factors"))
+    }
+}
+
+/// Deserialize an optional vertical field of view, in degrees.
+fn field_of_view<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<f64>::deserialize(deserializer)?;
+    if value.is_none_or(|vfov| vfov > 0.0 && vfov < 180.0) {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(
+            "expected an angle between 0 and 180 degrees",
+        ))
+    }
+}
+
+trait Positive {
+    fn is_positive(&self) -> bool;
+}
+
+impl Positive for f64 {
+    fn is_positive(&self) -> bool {
+        *self > 0.0
+    }
+}
+
+impl<T: Positive> Positive for Option<T> {
+    fn is_positive(&self) -> bool {
+        self.as_ref().is_none_or(T::is_positive)
+    }
+}
+
+impl Positive for usize {
+    fn is_positive(&self) -> bool {
+        *self > 0
+    }
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+/// Whether `a` and `b` are parallel, or either is zero.
+fn parallel(a: Vec3, b: Vec3) -> bool {
+    a.cross(b).length() <= 1e-9 * a.length() * b.length()
+}
+
+/// Add `object` to `world`, transformed if any of `scale`, `rotate`
+/// or `translate` are given.
+fn add_transformed<H: Hittable + 'static>(
+    world: &mut HittableList,
+    object: H,
+    scale: Option<[f64; 3]>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+) {
+    if scale.is_none() && rotate.is_none() && translate.is_none() {
+        world.add(object);
+        return;
+    }
+    let mut transform = Transform::identity();
+    if let Some(factors) = scale {
+        transform = transform.scale(vec3(factors));
+    }
+    if let Some([x, y, z]) = rotate {
+        transform = transform.rotate_x(x).rotate_y(y).rotate_z(z);
+    }
+    if let Some(offset) = translate {
+        transform = transform.translate(vec3(offset));
+    }
+    world.add(Transformed::new(object, transform));
+}
+
+impl SceneFile {
+    /// Load a scene file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SceneFile, SceneError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        SceneFile::parse_in(&std::fs::read_to_string(path)?, dir)
+    }
+
+    /// Parse the text of a scene file.
+    /// ```
+    /// use lib::{SceneError, SceneFile};
+    /// let scene = SceneFile::parse(r#"
+    ///     [materials.grey]
+    ///     type = "lambertian"
+    ///     albedo = [0.5, 0.5, 0.5]
+    ///
+    ///     [[spheres]]
+    ///     center = [0, 0, -1]
+    ///     radius = 0.5
+    ///     material = "grey"
+    /// "#).unwrap();
+    /// assert_eq!(scene.world.len(), 1);
+    ///
+    /// let err = SceneFile::parse("[camera]\nvfov = \"wide\"").err().unwrap();
+    /// assert!(matches!(err, SceneError::Parse { line: 2, column: 8, .. }));
+    /// ```
+    ///
+    /// Files the scene refers to are relative to the current directory.
+    pub fn parse(text: &str) -> Result<SceneFile, SceneError> {
+        SceneFile::parse_in(text, Path::new(""))
+    }
+
+    /// Parse the text of a scene file, with files relative to `dir`.
+    fn parse_in(text: &str, dir: &Path) -> Result<SceneFile, SceneError> {
+        let description: Description = toml::from_str(text).map_err(|err| {
+            SceneError::at(text, err.span().unwrap_or(0..0), err.message().to_owned())
+        })?;
+
+        let materials: HashMap<&str, Arc<dyn Material>> = description
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                let material: Arc<dyn Material> = match *material {
+                    MaterialDescription::Lambertian { albedo } => {
+                        Arc::new(Lambertian::new(vec3(albedo)))
+                    }
+                    MaterialDescription::Metal { albedo, fuzz } => {
+                        Arc::new(Metal::new(vec3(albedo), fuzz))
+                    }
+                    MaterialDescription::Dielectric { refraction_index } => {
+                        Arc::new(Dielectric::new(refraction_index))
+                    }
+                    MaterialDescription::Emissive { color, strength } => {
+                        Arc::new(Emissive::new(vec3(color), strength))
+                    }
+                };
+                (name.as_str(), material)
+            })
+            .collect();
+        let material = |name: &Spanned<String>| {
+            materials
+                .get(name.get_ref().as_str())
+                .cloned()
+                .ok_or_else(|| {
+                    SceneError::at(
+                        text,
+                        name.span(),
+                        format!("unknown material `{}`", name.get_ref()),
+                    )
+                })
+        };
+
+        let mut world = HittableList::new();
+        let emissive: HashSet<&str> = description
+            .materials
+            .iter()
+            .filter(|(_, material)| matches!(material, MaterialDescription::Emissive { .. }))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let is_light = |name: &Spanned<String>| emissive.contains(name.get_ref().as_str());
+        let mut lights = HittableList::new();
+
+        for sphere in &description.spheres {
+            let material = material(&sphere.material)?;
+            let object = Arc::new(Sphere::new(vec3(sphere.center), sphere.radius, material));
+            if is_light(&sphere.material) {
+                lights.add(object.clone());
+            }
+            world.add(object);
+        }
+        for plane in &description.planes {
+            let material = material(&plane.material)?;
+            world.add(Plane::new(vec3(plane.point), vec3(plane.normal), material));
+        }
+        for quad in &description.quads {
+            let material = material(&quad.material)?;
+            let (u, v) = (vec3(*quad.u.get_ref()), vec3(*quad.v.get_ref()));
+            if parallel(u, v) {
+                let side = if u == Vec3::zero() { &quad.u } else { &quad.v };
+                return Err(SceneError::at(
+                    text,
+                    side.span(),
+                    String::from("the sides of a quad must be nonzero and not parallel"),
+                ));
+            }
+            let object = Arc::new(Quad::new(vec3(quad.corner), u, v, material));
+            if is_light(&quad.material) {
+                lights.add(object.clone());
+            }
+            world.add(object);
+        }
+        for disk in &description.disks {
+            let material = material(&disk.material)?;
+            world.add(Disk::new(
+                vec3(disk.center),
+                vec3(disk.normal),
+                disk.radius,
+                material,
+            ));
+        }
+        for shape in &description.boxes {
+            let material = material(&shape.material)?;
+            let object = BoxShape::new(vec3(shape.from), vec3(shape.to), material);
+            add_transformed(
+                &mut world,
+                object,
+                shape.scale,
+                shape.rotate,
+                shape.translate,
+            );
+        }
+        for triangle in &description.triangles {
+            let [a, b, c] = triangle.vertices.map(vec3);
+            world.add(Triangle::new(a, b, c, material(&triangle.material)?));
+        }
+        let mut meshes: HashMap<&str, Arc<Mesh>> = HashMap::new();
+        for mesh in &description.meshes {
+            let file = mesh.file.get_ref();
+            let object = match meshes.get(file.as_str()) {
+                Some(object) => object.clone(),
+                None => {
+                    let object = obj::load(dir.join(file)).map_err(|err| {
+                        SceneError::at(text, mesh.file.span(), format!("cannot load mesh: {err}"))
+                    })?;
+                    meshes.entry(file).or_insert(Arc::new(object)).clone()
+                }
+            };
+            add_transformed(&mut world, object, mesh.scale, mesh.rotate, mesh.translate);
+        }
+
+        let mut path_tracer = PathTracer::default().lights(lights);
+        if let Some(max_depth) = description.camera.max_depth {
+            path_tracer = path_tracer.max_depth(max_depth);
+        }
+        if let Some(background) = &description.background {
+            path_tracer = match background.get_ref() {
+                BackgroundDescription::Gradient { bottom, top } => {
+                    path_tracer.background(Background::Gradient {
+                        bottom: vec3(*bottom),
+                        top: vec3(*top),
+                    })
+                }
+                BackgroundDescription::Solid { color } => {
+                    path_tracer.background(Background::Solid(vec3(*color)))
+                }
+                BackgroundDescription::None => path_tracer.background(Background::None),
+                BackgroundDescription::Image {
+                    file,
+                    rotation,
+                    intensity,
+                } => {
+                    let image = Image::load(dir.join(file)).map_err(|err| {
+                        SceneError::at(
+                            text,
+                            background.span(),
+                            format!("cannot load background {file}: {err}"),
+                        )
+                    })?;
+                    path_tracer.background(
+                        EnvironmentMap::new(image)
+                            .rotation(*rotation)
+                            .intensity(*intensity),
+                    )
+                }
+                BackgroundDescription::Sky {
+                    elevation,
+                    azimuth,
+                    turbidity,
+                    ground_albedo,
+                    intensity,
+                } => {
+                    let mut sky = Sky::from_angles(*elevation, *azimuth);
+                    if let Some(turbidity) = turbidity {
+                        sky = sky.turbidity(*turbidity);
+                    }
+                    if let Some(ground_albedo) = ground_albedo {
+                        sky = sky.ground_albedo(vec3(*ground_albedo));
+                    }
+                    if let Some(intensity) = intensity {
+                        sky = sky.intensity(*intensity);
+                    }
+                    path_tracer.background(sky)
+                }
+            };
+        }
+
+        Ok(SceneFile {
+            camera: description.camera.builder(text)?,
+            world: world.into_bvh(),
+            path_tracer,
+        })
+    }
+}
+
+impl CameraDescription {
+    /// The camera described, or an error for a camera which cannot
+    /// see: looking at its own position, or up along its view.
+    fn builder(&self, text: &str) -> Result<CameraBuilder, SceneError> {
+        // Positions default as in `CameraBuilder`.
+        let position = |key: &Option<Spanned<[f64; 3]>>, default: Vec3| {
+            key.as_ref().map_or(default, |value| vec3(*value.get_ref()))
+        };
+        let lookfrom = position(&self.lookfrom, Vec3::zero());
+        let lookat = position(&self.lookat, Vec3::new(0.0, 0.0, -1.0));
+        let vup = position(&self.vup, Vec3::new(0.0, 1.0, 0.0));
+        // The first of `keys` given, to point errors at
+        let span = |keys: &[&Option<Spanned<[f64; 3]>>]| {
+            keys.iter()
+                .find_map(|key| key.as_ref())
+                .map_or(0..0, |key| key.span())
+        };
+        if lookfrom == lookat {
+            return Err(SceneError::at(
+                text,
+                span(&[&self.lookat, &self.lookfrom]),
+                String::from("the camera looks at its own position"),
+            ));
+        }
+        if parallel(vup, lookat - lookfrom) {
+            return Err(SceneError::at(
+                text,
+                span(&[&self.vup, &self.lookat, &self.lookfrom]),
+                String::from("`vup` is along the view direction"),
+            ));
+        }
+
+        let mut camera = Camera::builder(1000, 16.0 / 9.0);
+        if let Some(width) = self.width {
+            camera = camera.image_width(width);
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera = camera.aspect_ratio(aspect_ratio);
+        }
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            camera = camera.samples_per_pixel(samples_per_pixel);
+        }
+        if let Some(vfov) = self.vfov {
+            camera = camera.vfov(vfov);
+        }
+        camera = camera.lookfrom(lookfrom).lookat(lookat).vup(vup);
+        if let Some(defocus_angle) = self.defocus_angle {
+            camera = camera.defocus_angle(defocus_angle);
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            camera = camera.focus_dist(focus_dist);
+        }
+        Ok(camera)
+    }
+}
+
+#[test]
+fn scene_error_test() {
+    let error = |text: &str| match SceneFile::parse(text) {
+        Err(SceneError::Parse {
+            line,
+            column,
+            message,
+        }) => (line, column, message),
+        Err(err) => panic!("unexpected error {err}"),
+        Ok(_) => panic!("{text:?} should not parse"),
+    };
+
+    let sphere = "[[spheres]]\ncenter = [0, 0, 0]\n";
+    let (line, column, message) = error(&format!("{sphere}radius = 1\nmaterial = \"steel\"\n"));
+    assert_eq!((line, column), (4, 12));
+    assert!(message.contains("unknown material `steel`"));
+
+    let (line, column, message) = error(&format!("{sphere}radius = -1\nmaterial = \"steel\"\n"));
+    assert_eq!((line, column), (3, 10));
+    assert!(message.contains("positive"));
+
+    let (line, column, message) = error("[camera]\nmax_depth = 0\n");
+    assert_eq!((line, column), (2, 13));
+    assert!(message.contains("positive"));
+
+    let (line, _, message) = error("[camera]\nwidth = 100\nfov = 20\n");
+    assert_eq!(line, 3);
+    assert!(message.contains("unknown field `fov`"));
+
+    let (line, _, message) = error("[materials.red]\ntype = \"plastic\"\n");
+    assert!(line <= 2);
+    assert!(message.contains("plastic"));
+
+    let (line, column, message) = error("[[meshes]]\nfile = \"missing.obj\"\n");
+    assert_eq!((line, column), (2, 8));
+    assert!(message.starts_with("cannot load mesh: missing.obj"));
+
+    let (line, column, message) =
+        error("[camera]\nwidth = 10\n[background]\ntype = \"image\"\nfile = \"sky.hdr\"\n");
+    assert_eq!((line, column), (3, 1));
+    assert!(message.starts_with("cannot load background sky.hdr"));
+
+    let (line, column, message) = error("[camera]\nlookfrom = [1, 2, 3]\nlookat = [1, 2, 3]\n");
+    assert_eq!((line, column), (3, 10));
+    assert!(message.contains("own position"));
+
+    let (line, column, message) = error("[camera]\nlookat = [0, 5, 0]\n");
+    assert_eq!((line, column), (2, 10));
+    assert!(message.contains("`vup`"));
+
+    let (line, column, _) = error("[camera]\nlookat = [1, 0, 0]\nvup = [-2, 0, 0]\n");
+    assert_eq!((line, column), (3, 7));
+
+    for vfov in ["0", "180", "-10", "200"] {
+        let (line, column, message) = error(&format!("[camera]\nvfov = {vfov}\n"));
+        assert_eq!((line, column), (2, 8));
+        assert!(message.contains("between 0 and 180"));
+    }
+
+    let (line, column, message) = error("[[planes]]\npoint = [0, 0, 0]\nnormal = [0, 0, 0]\n");
+    assert_eq!((line, column), (3, 10));
+    assert!(message.contains("nonzero vector"));
+    let (line, _, _) = error("[[disks]]\ncenter = [0, 0, 0]\nnormal = [0, 0, 0]\nradius = 1\n");
+    assert_eq!(line, 3);
+
+    let quad = |u: &str, v: &str| {
+        format!(
+            "[materials.white]\ntype = \"lambertian\"\nalbedo = [1, 1, 1]\n\
+             [[quads]]\ncorner = [0, 0, 0]\nu = {u}\nv = {v}\nmaterial = \"white\"\n"
+        )
+    };
+    let (line, column, message) = error(&quad("[1, 1, 0]", "[2, 2, 0]"));
+    assert_eq!((line, column), (7, 5));
+    assert!(message.contains("not parallel"));
+    assert_eq!(error(&quad("[0, 0, 0]", "[2, 2, 0]")).0, 6);
+    assert_eq!(error(&quad("[1, 0, 0]", "[0, 0, 0]")).0, 7);
+
+    let (line, column, message) = error("[[meshes]]\nfile = \"cube.obj\"\nscale = [1, 0, 1]\n");
+    assert_eq!((line, column), (3, 9));
+    assert!(message.contains("nonzero scale"));
+
+    let (line, column, _) = error("[camera\n");
+    assert_eq!((line, column), (1, 8));
+}
+
+#[test]
+fn scene_camera_test() {
+    let scene = SceneFile::parse(
+        "[camera]\nwidth = 200\naspect_ratio = 2\nlookat = [0, 0, -2]\n\
+         [background]\ntype = \"none\"\n",
+    )
+    .unwrap();
+    let camera = scene.camera.build();
+    assert_eq!((camera.image_width(), camera.image_height()), (200, 100));
+    assert!(scene.world.is_empty());
+    // Nothing to hit and a black background
+    let image = scene
+        .camera
+        .image_width(4)
+        .samples_per_pixel(1)
+        .build()
+        .render(&scene.world, &scene.path_tracer);
+    assert!(image.vec.iter().all(|c| *c == Vec3::zero()));
+}
+
+#[test]
+fn scene_transform_test() {
+    use crate::{Interval, Point3, Ray};
+
+    let scene = SceneFile::parse(
+        "[materials.white]\ntype = \"lambertian\"\nalbedo = [1, 1, 1]\n\
+         [[boxes]]\nfrom = [0, 0, 0]\nto = [1, 1, 1]\nmaterial = \"white\"\n\
+         scale = [2, 2, 2]\nrotate = [0, 90, 0]\ntranslate = [0, 0, 5]\n",
+    )
+    .unwrap();
+    // Scaled to [0, 2]³, rotated to x in [0, 2] and z in [-2, 0], then
+    // moved to z in [3, 5].
+    let bbox = scene.world.bounding_box();
+    assert!((bbox.z.min - 3.0).abs() < 1e-3 && (bbox.z.max - 5.0).abs() < 1e-3);
+    let ray = Ray::new(Point3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+    let hit = scene.world.hit(&ray, Interval::positive_or_null()).unwrap();
+    assert!((hit.t - 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn scene_sky_test() {
+    let scene = SceneFile::parse(
+        "[background]\ntype = \"sky\"\nelevation = 60\nturbidity = 4\n\
+         ground_albedo = [0, 0, 0]\n",
+    )
+    .unwrap();
+    // Looking at the horizon, over a black ground
+    let camera = Camera::builder(4, 1.0).samples_per_pixel(1).build();
+    let image = camera.render(&scene.world, &scene.path_tracer);
+    let sky = Sky::from_angles(60.0, 0.0)
+        .turbidity(4.0)
+        .ground_albedo(Vec3::zero());
+    let expected = camera.render(&scene.world, &PathTracer::default().background(sky));
+    assert!(image.vec == expected.vec);
+    assert!(image.vec[..8].iter().all(|c| c.z > 0.0));
+    assert!(image.vec[8..].iter().all(|c| *c == Vec3::zero()));
+}
+
+#[test]
+fn scene_light_test() {
+    // A camera inside a glowing sphere sees its light everywhere.
+    let scene = SceneFile::parse(
+        "[background]\ntype = \"none\"\n\
+         [materials.lamp]\ntype = \"emissive\"\ncolor = [1, 0.5, 0.25]\nstrength = 4\n\
+         [[spheres]]\ncenter = [0, 0, 0]\nradius = 10\nmaterial = \"lamp\"\n",
+    )
+    .unwrap();
+    let image = scene
+        .camera
+        .image_width(4)
+        .samples_per_pixel(1)
+        .build()
+        .render(&scene.world, &scene.path_tracer);
+    assert!(image.vec.iter().all(|c| *c == Vec3::new(4.0, 2.0, 1.0)));
+}
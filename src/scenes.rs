@@ -0,0 +1,656 @@
+//! Built-in scenes, from the early chapter demos to complete worlds.
+
+use crate::*;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Settings common to every scene, usually from the command line.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    /// Rendered image width in pixel count
+    pub image_width: usize,
+    pub aspect_ratio: f64,
+    /// Count of random samples for each pixel
+    pub samples_per_pixel: usize,
+    /// Maximum number of ray bounces into scene
+    pub max_depth: usize,
+    pub seed: u64,
+    /// Number of rendering threads
+    pub threads: usize,
+    pub integrator: IntegratorKind,
+}
+
+/// The integrators which can render any scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IntegratorKind {
+    /// The path tracer of the scene
+    #[default]
+    PathTracer,
+    /// [`Normals`]
+    Normals,
+    /// [`AmbientOcclusion`] within a distance
+    AmbientOcclusion(f64),
+}
+
+impl RenderSettings {
+    /// A camera builder configured with these settings.
+    pub fn camera(&self) -> CameraBuilder {
+        Camera::builder(self.image_width, self.aspect_ratio)
+            .samples_per_pixel(self.samples_per_pixel)
+            .seed(self.seed)
+            .threads(self.threads)
+    }
+
+    /// A path tracer configured with these settings.
+    pub fn path_tracer(&self) -> PathTracer {
+        PathTracer::new(self.max_depth)
+    }
+
+    /// The integrator to render with: `path_tracer`, unless another
+    /// one was chosen.
+    pub fn integrator(&self, path_tracer: PathTracer) -> Box<dyn Integrator> {
+        match self.integrator {
+            IntegratorKind::PathTracer => Box::new(path_tracer),
+            IntegratorKind::Normals => Box::new(Normals),
+            IntegratorKind::AmbientOcclusion(distance) => Box::new(AmbientOcclusion::new(distance)),
+        }
+    }
+
+    fn image_height(&self) -> usize {
+        ((self.image_width as f64 / self.aspect_ratio) as usize).max(1)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            image_width: 1000,
+            aspect_ratio: 16.0 / 9.0,
+            samples_per_pixel: 10,
+            max_depth: 10,
+            seed: 0,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            integrator: IntegratorKind::default(),
+        }
+    }
+}
+
+/// A named, built-in scene.
+pub struct Scene {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Aspect ratio the scene is framed for, if it is not the default
+    pub aspect_ratio: Option<f64>,
+    render: fn(&RenderSettings) -> Image,
+}
+
+impl Scene {
+    pub fn render(&self, settings: &RenderSettings) -> Image {
+        (self.render)(settings)
+    }
+}
+
+/// All built-in scenes.  The first ones are the early chapter demos,
+/// which draw their pixels directly and ignore sampling settings.
+pub const SCENES: &[Scene] = &[
+    Scene {
+        name: "gradient",
+        description: "Just producing a stupid image.",
+        aspect_ratio: Some(1.0),
+        render: gradient,
+    },
+    Scene {
+        name: "sky",
+        description: "Raytraced background.",
+        aspect_ratio: None,
+        render: sky,
+    },
+    Scene {
+        name: "red-sphere",
+        description: "Sphere.",
+        aspect_ratio: None,
+        render: red_sphere,
+    },
+    Scene {
+        name: "normals",
+        description: "Sphere with normals.",
+        aspect_ratio: None,
+        render: normals,
+    },
+    Scene {
+        name: "world-normals",
+        description: "A world of Sphere.",
+        aspect_ratio: None,
+        render: world_normals,
+    },
+    Scene {
+        name: "diffuse",
+        description: "Antialiasing, diffuse spheres.",
+        aspect_ratio: None,
+        render: diffuse,
+    },
+    Scene {
+        name: "materials",
+        description: "Diffuse, metal and glass spheres.",
+        aspect_ratio: None,
+        render: materials,
+    },
+    Scene {
+        name: "random-spheres",
+        description: "Hundreds of random spheres, with defocus blur.",
+        aspect_ratio: None,
+        render: random_spheres,
+    },
+    Scene {
+        name: "checkered-spheres",
+        description: "Two spheres with a checker texture.",
+        aspect_ratio: None,
+        render: checkered_spheres,
+    },
+    Scene {
+        name: "perlin-spheres",
+        description: "A marble sphere on a turbulent ground.",
+        aspect_ratio: None,
+        render: perlin_spheres,
+    },
+    Scene {
+        name: "quads",
+        description: "Five colored quads.",
+        aspect_ratio: None,
+        render: quads,
+    },
+    Scene {
+        name: "cornell-box",
+        description: "The Cornell box.",
+        aspect_ratio: Some(1.0),
+        render: cornell_box,
+    },
+    Scene {
+        name: "daylight",
+        description: "Blocks and spheres in the afternoon sun.",
+        aspect_ratio: None,
+        render: daylight,
+    },
+];
+
+/// Find a built-in scene by name.
+/// ```
+/// use lib::scenes;
+/// assert!(scenes::find("diffuse").is_some());
+/// assert!(scenes::find("teapot").is_none());
+/// ```
+pub fn find(name: &str) -> Option<&'static Scene> {
+    SCENES.iter().find(|scene| scene.name == name)
+}
+
+fn gradient(settings: &RenderSettings) -> Image {
+    let mut image = Image::new(settings.image_width, settings.image_height());
+
+    for x in 0..image.width() {
+        for y in 0..image.height() {
+            image[(x, y)].x = x as f64 / image.width() as f64;
+            image[(x, y)].y = y as f64 / image.height() as f64;
+        }
+    }
+    image
+}
+
+/// The camera of the chapter demos: a viewport two units high, one
+/// unit in front of the origin.  Pixel `(i, j)` is counted from the
+/// lower left corner.
+fn chapter_render(settings: &RenderSettings, ray_color: &dyn Fn(&Ray) -> Color) -> Image {
+    let image_width = settings.image_width;
+    let image_height = settings.image_height();
+    let mut image = Image::new(image_width, image_height);
+
+    let viewport_height = 2.0;
+    let viewport_width = settings.aspect_ratio * viewport_height;
+    let focal_length = 1.0;
+
+    let origin = Point3::new(0.0, 0.0, 0.0);
+    let horizontal = Vec3::new(viewport_width, 0.0, 0.0);
+    let vertical = Vec3::new(0.0, viewport_height, 0.0);
+    let lower_left_corner =
+        origin - horizontal / 2.0 - vertical / 2.0 - Vec3::new(0.0, 0.0, focal_length);
+
+    for j in 0..image_height {
+        for i in 0..image_width {
+            // u and v are width and height as part of the total image
+            // size.  A single row or column stays on the edge.
+            let u = i as f64 / (image_width - 1).max(1) as f64;
+            let v = j as f64 / (image_height - 1).max(1) as f64;
+            let r = Ray::new(
+                origin,
+                lower_left_corner + u * horizontal + v * vertical - origin,
+            );
+            // We reverse the y position because our reference point
+            // is the lower-left corner, but we encode image top to
+            // bottom.
+            image[(i, image_height - j - 1)] = ray_color(&r);
+        }
+    }
+    image
+}
+
+fn sky(settings: &RenderSettings) -> Image {
+    chapter_render(settings, &|r| {
+        let unit_direction = r.direction.unit_vector();
+        let t = 0.5 * (unit_direction.y + 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let blue = Color::new(0.3, 0.5, 1.0);
+        (1.0 - t) * white + t * blue
+    })
+}
+
+/// Determine if ray r hits the sphere at `center` with `radius`, and
+/// where.
+fn hit_sphere(center: &Point3, radius: f64, r: &Ray) -> Option<f64> {
+    /* The sphere equation for the sphere of radius R is: x² + y² + z²
+    = R².  Given a ray r, we want to know if there's a point on that
+    ray that satisfies this equation. */
+    let oc: Vec3 = r.origin - *center;
+    let a = r.direction.length_squared();
+    let half_b = oc.dot(r.direction);
+    let c = oc.length_squared() - radius * radius;
+    let discriminant = half_b * half_b - a * c;
+
+    if discriminant < 0.0 {
+        None
+    } else {
+        Some((-half_b - discriminant.sqrt()) / a)
+    }
+}
+
+fn red_sphere(settings: &RenderSettings) -> Image {
+    chapter_render(settings, &|r| {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let blue = Color::new(0.3, 0.5, 1.0);
+        let red = Color::new(1.0, 0.0, 0.0);
+
+        if hit_sphere(&Point3::new(0.0, 0.0, -1.0), 0.5, r).is_some() {
+            red
+        } else {
+            let unit_direction = r.direction.unit_vector();
+            let t = 0.5 * (unit_direction.y + 1.0);
+
+            (1.0 - t) * white + t * blue
+        }
+    })
+}
+
+fn normals(settings: &RenderSettings) -> Image {
+    chapter_render(settings, &|ray| {
+        let grad_start = Color::new(0.3, 0.3, 0.3);
+        let grad_end = Color::new(0.1, 0.1, 0.1);
+        let sph_center = Point3::new(0.0, 0.0, -1.0);
+        let sph_radius = 0.5;
+
+        if let Some(t) = hit_sphere(&sph_center, sph_radius, ray) {
+            let n = (ray.at(t) - sph_center).unit_vector();
+            0.5 * Color::new(n.x + 1.0, n.y + 1.0, n.z + 1.0)
+        } else {
+            let unit_direction = ray.direction.unit_vector();
+            let t = 0.5 * (unit_direction.y + 1.0);
+            (1.0 - t) * grad_end + t * grad_start
+        }
+    })
+}
+
+/// The two spheres of the early chapters.
+fn two_spheres() -> Vec<Sphere> {
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    vec![
+        Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material.clone()),
+        Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material),
+    ]
+}
+
+fn world_normals(settings: &RenderSettings) -> Image {
+    let world = two_spheres();
+    chapter_render(settings, &|ray| {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let grad_end = Color::new(0.5, 0.7, 1.0);
+
+        if let Some(hit) = world.hit(ray, Interval::positive_or_null()) {
+            0.5 * (hit.normal + white)
+        } else {
+            let unit_direction = ray.direction.unit_vector();
+            let t = 0.5 * (unit_direction.y + 1.0);
+            (1.0 - t) * white + t * grad_end
+        }
+    })
+}
+
+fn diffuse(settings: &RenderSettings) -> Image {
+    settings
+        .camera()
+        .build()
+        .render(&two_spheres(), &settings.integrator(settings.path_tracer()))
+}
+
+fn materials(settings: &RenderSettings) -> Image {
+    let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let center: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let left: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let bubble: Arc<dyn Material> = Arc::new(Dielectric::new(1.0 / 1.5));
+    let right: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.5));
+
+    let world = vec![
+        Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground),
+        Sphere::new(Point3::new(0.0, 0.0, -1.2), 0.5, center),
+        Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5, left),
+        Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.4, bubble),
+        Sphere::new(Point3::new(1.0, 0.0, -1.0), 0.5, right),
+    ];
+
+    settings
+        .camera()
+        .build()
+        .render(&world, &settings.integrator(settings.path_tracer()))
+}
+
+fn random_spheres(settings: &RenderSettings) -> Image {
+    // The scene layout depends on the seed too.
+    let mut rng = sampler::pixel_sampler(settings.seed, usize::MAX);
+    let mut world = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Point3::new(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() <= 0.9 {
+                continue;
+            }
+
+            let choose_mat: f64 = rng.gen();
+            let material: Arc<dyn Material> = if choose_mat < 0.8 {
+                let albedo = Color::random(&mut rng) * Color::random(&mut rng);
+                Arc::new(Lambertian::new(albedo))
+            } else if choose_mat < 0.95 {
+                let albedo = Color::random_range(&mut rng, 0.5, 1.0);
+                Arc::new(Metal::new(albedo, rng.gen_range(0.0..0.5)))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            world.add(Sphere::new(center, 0.2, material));
+        }
+    }
+
+    world.add(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))),
+    ));
+    world.add(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    ));
+
+    settings
+        .camera()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::new(0.0, 0.0, 0.0))
+        .defocus_angle(0.6)
+        .focus_dist(10.0)
+        .build()
+        .render(
+            &world.into_bvh(),
+            &settings.integrator(settings.path_tracer()),
+        )
+}
+
+fn checkered_spheres(settings: &RenderSettings) -> Image {
+    let checker = Arc::new(Lambertian::textured(Arc::new(Checker::colors(
+        0.32,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ))));
+    let world = vec![
+        Sphere::new(Point3::new(0.0, -10.0, 0.0), 10.0, checker.clone()),
+        Sphere::new(Point3::new(0.0, 10.0, 0.0), 10.0, checker),
+    ];
+
+    settings
+        .camera()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::zero())
+        .build()
+        .render(&world, &settings.integrator(settings.path_tracer()))
+}
+
+fn perlin_spheres(settings: &RenderSettings) -> Image {
+    // The noise depends on the seed too.
+    let mut rng = sampler::pixel_sampler(settings.seed, usize::MAX);
+    let ground = NoiseTexture::new(NoiseKind::Turbulence, 4.0, &mut rng);
+    let marble = NoiseTexture::new(NoiseKind::Marble, 4.0, &mut rng);
+    let world = vec![
+        Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            Arc::new(Lambertian::textured(Arc::new(ground))),
+        ),
+        Sphere::new(
+            Point3::new(0.0, 2.0, 0.0),
+            2.0,
+            Arc::new(Lambertian::textured(Arc::new(marble))),
+        ),
+    ];
+
+    settings
+        .camera()
+        .vfov(20.0)
+        .lookfrom(Point3::new(13.0, 2.0, 3.0))
+        .lookat(Point3::zero())
+        .build()
+        .render(&world, &settings.integrator(settings.path_tracer()))
+}
+
+fn quads(settings: &RenderSettings) -> Image {
+    let material =
+        |r, g, b| -> Arc<dyn Material> { Arc::new(Lambertian::new(Color::new(r, g, b))) };
+    let left_red = material(1.0, 0.2, 0.2);
+    let back_green = material(0.2, 1.0, 0.2);
+    let right_blue = material(0.2, 0.2, 1.0);
+    let upper_orange = material(1.0, 0.5, 0.0);
+    let lower_teal = material(0.2, 0.8, 0.8);
+
+    let world = vec![
+        Quad::new(
+            Point3::new(-3.0, -2.0, 5.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            left_red,
+        ),
+        Quad::new(
+            Point3::new(-2.0, -2.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            back_green,
+        ),
+        Quad::new(
+            Point3::new(3.0, -2.0, 1.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            right_blue,
+        ),
+        Quad::new(
+            Point3::new(-2.0, 3.0, 1.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 4.0),
+            upper_orange,
+        ),
+        Quad::new(
+            Point3::new(-2.0, -3.0, 5.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            lower_teal,
+        ),
+    ];
+
+    settings
+        .camera()
+        .vfov(80.0)
+        .lookfrom(Point3::new(0.0, 0.0, 9.0))
+        .lookat(Point3::zero())
+        .build()
+        .render(&world, &settings.integrator(settings.path_tracer()))
+}
+
+/// The Cornell box of the book: a 555 units wide room, seen with its
+/// green wall on the left and red wall on the right, with a tall and
+/// a short box, lit only by a light in the ceiling.
+fn cornell_box(settings: &RenderSettings) -> Image {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        Arc::new(Emissive::new(Color::new(1.0, 1.0, 1.0), 15.0)),
+    ));
+
+    let mut world = HittableList::new();
+    world.add(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green,
+    ));
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    ));
+    world.add(light.clone());
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    ));
+    world.add(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    ));
+    world.add(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white.clone(),
+    ));
+
+    let tall = BoxShape::new(
+        Point3::zero(),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    );
+    world.add(Transformed::new(
+        tall,
+        Transform::identity()
+            .rotate_y(15.0)
+            .translate(Vec3::new(265.0, 0.0, 295.0)),
+    ));
+    let short = BoxShape::new(Point3::zero(), Point3::new(165.0, 165.0, 165.0), white);
+    world.add(Transformed::new(
+        short,
+        Transform::identity()
+            .rotate_y(-18.0)
+            .translate(Vec3::new(130.0, 0.0, 65.0)),
+    ));
+
+    let mut lights = HittableList::new();
+    lights.add(light);
+    let tracer = settings
+        .path_tracer()
+        .background(Background::None)
+        .lights(lights);
+    settings
+        .camera()
+        .vfov(40.0)
+        .lookfrom(Point3::new(278.0, 278.0, -800.0))
+        .lookat(Point3::new(278.0, 278.0, 0.0))
+        .build()
+        .render(&world.into_bvh(), &settings.integrator(tracer))
+}
+
+/// A few blocks and spheres on a plaza, under a clear sky with the
+/// sun low on the left, behind the camera.
+fn daylight(settings: &RenderSettings) -> Image {
+    let stone: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.6, 0.55, 0.5)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let brick: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.55, 0.25, 0.15)));
+
+    let mut world = HittableList::new();
+    world.add(Plane::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), stone));
+    world.add(Transformed::new(
+        BoxShape::new(Point3::zero(), Point3::new(2.0, 4.0, 2.0), white.clone()),
+        Transform::identity()
+            .rotate_y(30.0)
+            .translate(Vec3::new(-4.0, 0.0, -6.0)),
+    ));
+    world.add(Transformed::new(
+        BoxShape::new(Point3::zero(), Point3::new(3.0, 1.5, 1.5), brick),
+        Transform::identity()
+            .rotate_y(-20.0)
+            .translate(Vec3::new(1.5, 0.0, -7.0)),
+    ));
+    world.add(Sphere::new(Point3::new(-1.0, 1.0, -2.0), 1.0, white));
+    world.add(Sphere::new(
+        Point3::new(1.5, 0.7, -2.5),
+        0.7,
+        Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0)),
+    ));
+    world.add(Sphere::new(
+        Point3::new(0.3, 0.5, 0.0),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+
+    let tracer = settings
+        .path_tracer()
+        .background(Sky::from_angles(25.0, -130.0));
+    settings
+        .camera()
+        .vfov(45.0)
+        .lookfrom(Point3::new(0.0, 1.8, 5.0))
+        .lookat(Point3::new(0.0, 1.0, -3.0))
+        .build()
+        .render(&world.into_bvh(), &settings.integrator(tracer))
+}
+
+#[test]
+fn chapter_render_test() {
+    // A single pixel, in either direction, is still finite.
+    for (image_width, aspect_ratio) in [(1, 1.0), (1, 0.5), (8, 8.0), (8, 100.0)] {
+        let settings = RenderSettings {
+            image_width,
+            aspect_ratio,
+            ..Default::default()
+        };
+        let image = sky(&settings);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let c = image[(x, y)];
+                assert!(c.x.is_finite() && c.y.is_finite() && c.z.is_finite());
+            }
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Textures: colors varying over surfaces, looked up by surface
+//! coordinates or by position in space.
+
+use crate::image::Image;
+use crate::vec3::{Color, Point3, Vec3};
+use rand::Rng;
+use std::sync::Arc;
+
+/// A color which varies over surfaces.  Textures are shared between
+/// rendering threads, hence the `Send + Sync` bound.
+pub trait Texture: Send + Sync {
+    /// The color at surface coordinates `u`, `v` of point `p`.
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+/// The same color everywhere.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.color
+    }
+}
+
+/// A checkerboard of cubes of side `scale` filling space, alternating
+/// between two textures.
+/// ```
+/// use lib::{Checker, Color, Point3, Texture};
+/// let checker = Checker::colors(0.5, Color::zero(), Color::new(1.0, 1.0, 1.0));
+/// assert!(checker.value(0.0, 0.0, Point3::new(0.1, 0.1, 0.1)) == Color::zero());
+/// assert!(checker.value(0.0, 0.0, Point3::new(0.6, 0.1, 0.1)) == Color::new(1.0, 1.0, 1.0));
+/// ```
+pub struct Checker {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    /// A checkerboard of `even` and `odd`.
+    ///
+    /// # Panics
+    ///
+    /// If `scale` is not positive.
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Checker {
+        assert!(scale > 0.0, "Invalid checker scale {scale}");
+        Checker {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    /// A checkerboard of two colors.
+    pub fn colors(scale: f64, even: Color, odd: Color) -> Checker {
+        Checker::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        // Parity is taken in floating point, where far away cells
+        // cannot overflow.
+        let cell = |x: f64| (self.inv_scale * x).floor();
+        if (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// How image textures extend beyond surface coordinates [0, 1].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    /// Tile the image.
+    #[default]
+    Repeat,
+    /// Stretch the pixels of the edges.
+    Clamp,
+    /// Tile the image, flipping every other copy.
+    Mirror,
+}
+
+impl Wrap {
+    /// The pixel of an image `size` pixels wide or high standing for
+    /// pixel `i`.
+    fn index(self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(size),
+            Wrap::Clamp => i.clamp(0, size - 1),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size {
+                    i
+                } else {
+                    2 * size - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+/// An image stretched over surface coordinates, `u` going from left
+/// to right and `v` from bottom to top, and filtered bilinearly.
+/// ```
+/// use lib::{Color, Image, ImageTexture, Point3, Texture, Wrap};
+/// let image = Image::from_vec(2, 1, vec![Color::zero(), Color::new(1.0, 1.0, 1.0)]);
+/// let texture = ImageTexture::new(image, Wrap::Clamp);
+/// // Halfway between the centers of the two pixels
+/// assert!(texture.value(0.5, 0.5, Point3::zero()) == Color::new(0.5, 0.5, 0.5));
+/// assert!(texture.value(-3.0, 0.5, Point3::zero()) == Color::zero());
+/// ```
+pub struct ImageTexture {
+    image: Image,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image, wrap: Wrap) -> ImageTexture {
+        ImageTexture { image, wrap }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        if width == 0 || height == 0 {
+            // Cyan stands out, to debug missing images.
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        // Pixel centers are at half-integer coordinates, and rows go
+        // from top to bottom.
+        let x = u * width as f64 - 0.5;
+        let y = (1.0 - v) * height as f64 - 0.5;
+        // Coordinates too large for exact pixels, as at grazing hits on
+        // planes, saturate.
+        let (x0, y0) = (x.floor(), y.floor());
+        let fraction = |t: f64, t0: f64| if t.is_finite() { t - t0 } else { 0.0 };
+        let (fx, fy) = (fraction(x, x0), fraction(y, y0));
+        let pixel = |dx: i64, dy: i64| {
+            let i = self.wrap.index((x0 as i64).saturating_add(dx), width);
+            let j = self.wrap.index((y0 as i64).saturating_add(dy), height);
+            self.image[(i, j)]
+        };
+        let top = (1.0 - fx) * pixel(0, 0) + fx * pixel(1, 0);
+        let bottom = (1.0 - fx) * pixel(0, 1) + fx * pixel(1, 1);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+const POINT_COUNT: usize = 256;
+
+/// Perlin noise: a smooth random function of space, between -1 and 1.
+pub struct Perlin {
+    vectors: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT],
+}
+
+impl Perlin {
+    /// Noise drawn from `rng`.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Perlin {
+        let mut vectors = [Vec3::zero(); POINT_COUNT];
+        for vector in &mut vectors {
+            *vector = Vec3::random_unit_vector(rng);
+        }
+        let mut permutation = || {
+            let mut p: [usize; POINT_COUNT] = std::array::from_fn(|i| i);
+            for i in (1..POINT_COUNT).rev() {
+                p.swap(i, rng.gen_range(0..=i));
+            }
+            p
+        };
+        Perlin {
+            perm_x: permutation(),
+            perm_y: permutation(),
+            perm_z: permutation(),
+            vectors,
+        }
+    }
+
+    /// The noise at `p`, interpolated between random gradients at the
+    /// corners of the unit cube around it.
+    pub fn noise(&self, p: Point3) -> f64 {
+        let floor = [p.x.floor(), p.y.floor(), p.z.floor()];
+        let [u, v, w] = [p.x - floor[0], p.y - floor[1], p.z - floor[2]];
+        let [i, j, k] = floor.map(|x| x as i64);
+
+        // Hermite smoothing hides the grid.
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = |perm: &[usize; POINT_COUNT], x: i64| {
+                        perm[(x & (POINT_COUNT as i64 - 1)) as usize]
+                    };
+                    let gradient = self.vectors[index(&self.perm_x, i + di)
+                        ^ index(&self.perm_y, j + dj)
+                        ^ index(&self.perm_z, k + dk)];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    sum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(weight);
+                }
+            }
+        }
+        sum
+    }
+
+    /// Sum of `depth` octaves of noise, each twice as fine and half as
+    /// strong as the previous one, between 0 and about 1.
+    pub fn turbulence(&self, p: Point3, depth: usize) -> f64 {
+        let mut sum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            sum += weight * self.noise(p);
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        sum.abs()
+    }
+}
+
+/// The look of a [`NoiseTexture`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Plain Perlin noise, soft grey blobs
+    Noise,
+    /// Turbulence, like a camouflage net
+    Turbulence,
+    /// Stripes along Z, distorted by turbulence
+    Marble,
+}
+
+/// Grey patterns made from Perlin noise, finer as `scale` grows.
+pub struct NoiseTexture {
+    perlin: Perlin,
+    kind: NoiseKind,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new<R: Rng + ?Sized>(kind: NoiseKind, scale: f64, rng: &mut R) -> NoiseTexture {
+        NoiseTexture {
+            perlin: Perlin::new(rng),
+            kind,
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let grey = match self.kind {
+            NoiseKind::Noise => 0.5 * (1.0 + self.perlin.noise(self.scale * p)),
+            NoiseKind::Turbulence => self.perlin.turbulence(self.scale * p, 7),
+            NoiseKind::Marble => {
+                0.5 * (1.0 + (self.scale * p.z + 10.0 * self.perlin.turbulence(p, 7)).sin())
+            }
+        };
+        Color::new(grey, grey, grey)
+    }
+}
+
+#[test]
+fn wrap_test() {
+    let indices = |wrap: Wrap| (-4..7).map(|i| wrap.index(i, 3)).collect::<Vec<_>>();
+    assert_eq!(indices(Wrap::Repeat), [2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0]);
+    assert_eq!(indices(Wrap::Clamp), [0, 0, 0, 0, 0, 1, 2, 2, 2, 2, 2]);
+    assert_eq!(indices(Wrap::Mirror), [2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0]);
+
+    // A 2×2 image, dark at the bottom: v goes up.
+    let (dark, light) = (Color::zero(), Color::new(1.0, 1.0, 1.0));
+    let image = Image::from_vec(2, 2, vec![light, light, dark, dark]);
+    let texture = ImageTexture::new(image, Wrap::Repeat);
+    assert!(texture.value(0.25, 0.25, Point3::zero()) == dark);
+    assert!(texture.value(0.75, 0.75, Point3::zero()) == light);
+    // Between rows, and across the edge of the repeated image
+    assert!(texture.value(0.3, 0.5, Point3::zero()) == Color::new(0.5, 0.5, 0.5));
+    assert!(texture.value(0.3, 0.0, Point3::zero()) == Color::new(0.5, 0.5, 0.5));
+
+    // Huge and infinite coordinates still fall on pixels.
+    for wrap in [Wrap::Repeat, Wrap::Clamp, Wrap::Mirror] {
+        let texture = ImageTexture::new(Image::from_vec(1, 1, vec![light]), wrap);
+        for u in [1e30, -1e30, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(texture.value(u, -u, Point3::zero()) == light);
+        }
+    }
+}
+
+#[test]
+fn checker_test() {
+    let (black, white) = (Color::zero(), Color::new(1.0, 1.0, 1.0));
+    let checker = Checker::colors(1.0, black, white);
+    assert!(checker.value(0.0, 0.0, Point3::new(-0.5, 0.5, 0.5)) == white);
+    assert!(checker.value(0.0, 0.0, Point3::new(-0.5, -0.5, 0.5)) == black);
+
+    // Cells too small to count do not overflow.
+    let tiny = Checker::colors(1e-300, black, white);
+    let color = tiny.value(0.0, 0.0, Point3::new(3.0, -2.0, 1.0));
+    assert!(color == black || color == white);
+}
+
+#[test]
+fn perlin_test() {
+    use crate::sampler::pixel_sampler;
+
+    let perlin = Perlin::new(&mut pixel_sampler(0, 0));
+    // Noise vanishes on the lattice, and varies smoothly elsewhere.
+    assert!(perlin.noise(Point3::new(3.0, -2.0, 7.0)) == 0.0);
+    let p = Point3::new(0.3, 1.7, -4.2);
+    let step = Vec3::new(1e-6, 0.0, 0.0);
+    assert!((perlin.noise(p) - perlin.noise(p + step)).abs() < 1e-5);
+
+    let mut rng = pixel_sampler(0, 1);
+    let values: Vec<f64> = (0..1000)
+        .map(|_| perlin.noise(10.0 * Vec3::random(&mut rng)))
+        .collect();
+    assert!(values.iter().all(|n| n.abs() <= 1.0));
+    assert!(values.iter().any(|&n| n > 0.2) && values.iter().any(|&n| n < -0.2));
+
+    // The same draws give the same noise.
+    let again = Perlin::new(&mut pixel_sampler(0, 0));
+    assert!(again.noise(p) == perlin.noise(p));
+}
+
+#[test]
+#[should_panic(expected = "Invalid checker scale")]
+fn checker_scale_test() {
+    Checker::colors(0.0, Color::zero(), Color::zero());
+}
@@ -0,0 +1,140 @@
+use crate::interval::Interval;
+use crate::vec3::Color;
+
+/// Tone mapping operators, compressing linear radiance into the
+/// displayable 0..1 range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Cut everything above 1.
+    Clamp,
+    /// `x / (1 + x)`
+    Reinhard,
+    /// Reinhard, with `white` being the smallest value mapped to 1.
+    /// A `white` which is not positive maps everything but black
+    /// to 1.
+    ExtendedReinhard { white: f64 },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMap {
+    /// Map a single linear channel value.
+    /// ```
+    /// use lib::ToneMap;
+    /// assert!(ToneMap::Clamp.map(2.0) == 1.0);
+    /// assert!(ToneMap::Reinhard.map(1.0) == 0.5);
+    /// assert!(ToneMap::ExtendedReinhard { white: 4.0 }.map(4.0) == 1.0);
+    /// for white in [0.0, -1.0, f64::NAN, 1e-200] {
+    ///     let tone_map = ToneMap::ExtendedReinhard { white };
+    ///     assert!(tone_map.map(0.0) == 0.0 && tone_map.map(0.01) == 1.0);
+    /// }
+    /// assert!(ToneMap::Aces.map(0.0) == 0.0);
+    /// assert!(ToneMap::Aces.map(100.0) > 0.99);
+    /// ```
+    pub fn map(self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self {
+            ToneMap::Clamp => x.min(1.0),
+            ToneMap::Reinhard => x / (1.0 + x),
+            // Dividing twice, so that a tiny white does not underflow.
+            ToneMap::ExtendedReinhard { white } if white > 0.0 => {
+                (x * (1.0 + x / white / white) / (1.0 + x)).min(1.0)
+            }
+            ToneMap::ExtendedReinhard { .. } => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ToneMap::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// How linear radiance is turned into a displayable image: an
+/// exposure adjustment, a tone mapping operator, then the sRGB
+/// transfer function.
+///
+/// Looks are applied when writing integer formats (PPM, PNG); the
+/// image itself always stays linear.
+/// ```
+/// use lib::{Color, Look, ToneMap};
+/// let look = Look::new(ToneMap::Reinhard).exposure(1.0);
+/// // One stop up doubles 0.5 to 1.0, which Reinhard maps to 0.5,
+/// // encoded as sRGB.
+/// let encoded = look.apply(Color::new(0.5, 0.5, 0.5));
+/// assert!((encoded.x - 0.735357).abs() < 1e-6);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Look {
+    /// Exposure adjustment, in stops
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+}
+
+impl Look {
+    pub fn new(tone_map: ToneMap) -> Look {
+        Look {
+            exposure: 0.0,
+            tone_map,
+        }
+    }
+
+    /// Set the exposure adjustment, in stops.
+    pub fn exposure(mut self, stops: f64) -> Look {
+        self.exposure = stops;
+        self
+    }
+
+    /// Convert linear radiance to sRGB encoded values in 0..1.
+    pub fn apply(&self, color: Color) -> Color {
+        let scale = 2f64.powf(self.exposure);
+        let encode = |c: f64| srgb_encode(self.tone_map.map(c * scale));
+        Color::new(encode(color.x), encode(color.y), encode(color.z))
+    }
+}
+
+impl Default for Look {
+    fn default() -> Self {
+        Look::new(ToneMap::Clamp)
+    }
+}
+
+/// The sRGB opto-electronic transfer function: encode a linear value
+/// in 0..1.
+/// ```
+/// use lib::tonemap::srgb_encode;
+/// assert!(srgb_encode(0.0) == 0.0);
+/// assert!((srgb_encode(0.001) - 0.01292).abs() < 1e-12);
+/// assert!((srgb_encode(0.5) - 0.735357).abs() < 1e-6);
+/// assert!((srgb_encode(1.0) - 1.0).abs() < 1e-12);
+/// ```
+pub fn srgb_encode(linear: f64) -> f64 {
+    let linear = Interval::new(0.0, 1.0).clamp(linear);
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The inverse of [`srgb_encode`]: decode an sRGB value in 0..1 to
+/// linear.
+/// ```
+/// use lib::tonemap::{srgb_decode, srgb_encode};
+/// for x in [0.0, 0.002, 0.2, 0.5, 1.0] {
+///     assert!((srgb_decode(srgb_encode(x)) - x).abs() < 1e-12);
+/// }
+/// ```
+pub fn srgb_decode(encoded: f64) -> f64 {
+    let encoded = Interval::new(0.0, 1.0).clamp(encoded);
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
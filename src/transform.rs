@@ -0,0 +1,336 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
+
+type Matrix4 = [[f64; 4]; 4];
+
+const IDENTITY: Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// An affine transformation of space, kept along with its inverse.
+///
+/// Transformations are built by chaining, each one applying after
+/// the previous ones:
+/// ```
+/// use lib::{Transform, Vec3};
+/// let t = Transform::identity()
+///     .scale(Vec3::new(2.0, 2.0, 2.0))
+///     .rotate_z(90.0)
+///     .translate(Vec3::new(0.0, 0.0, 1.0));
+/// let p = t.point(Vec3::new(1.0, 0.0, 0.0));
+/// assert!((p - Vec3::new(0.0, 2.0, 1.0)).length() < 1e-12);
+/// assert!((t.inverse().point(p) - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: Matrix4,
+    inv: Matrix4,
+}
+
+impl Transform {
+    pub const fn identity() -> Transform {
+        Transform {
+            m: IDENTITY,
+            inv: IDENTITY,
+        }
+    }
+
+    /// Apply `other` after this transformation.
+    pub fn then(self, other: Transform) -> Transform {
+        Transform {
+            m: multiply(&other.m, &self.m),
+            inv: multiply(&self.inv, &other.inv),
+        }
+    }
+
+    pub fn inverse(self) -> Transform {
+        Transform {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    /// Then move by `offset`.
+    pub fn translate(self, offset: Vec3) -> Transform {
+        let mut t = Transform::identity();
+        for i in 0..3 {
+            t.m[i][3] = offset[i];
+            t.inv[i][3] = -offset[i];
+        }
+        self.then(t)
+    }
+
+    /// Then scale by `factors` along each axis, which must not be
+    /// zero.  Negative factors mirror.
+    pub fn scale(self, factors: Vec3) -> Transform {
+        let mut t = Transform::identity();
+        for i in 0..3 {
+            t.m[i][i] = factors[i];
+            t.inv[i][i] = 1.0 / factors[i];
+        }
+        self.then(t)
+    }
+
+    /// Then rotate by `degrees` around `axis`, counterclockwise when
+    /// the axis points towards the viewer.
+    pub fn rotate(self, axis: Vec3, degrees: f64) -> Transform {
+        let Vec3 { x, y, z } = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let c = 1.0 - cos;
+        let mut t = Transform::identity();
+        let r = [
+            [cos + x * x * c, x * y * c - z * sin, x * z * c + y * sin],
+            [y * x * c + z * sin, cos + y * y * c, y * z * c - x * sin],
+            [z * x * c - y * sin, z * y * c + x * sin, cos + z * z * c],
+        ];
+        // A rotation's inverse is its transpose.
+        for (i, row) in r.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                t.m[i][j] = value;
+                t.inv[j][i] = value;
+            }
+        }
+        self.then(t)
+    }
+
+    pub fn rotate_x(self, degrees: f64) -> Transform {
+        self.rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(self, degrees: f64) -> Transform {
+        self.rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(self, degrees: f64) -> Transform {
+        self.rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    /// Transform a point.
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    /// Transform a direction, which translations do not affect.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let row = |r: &[f64; 4]| r[0] * v.x + r[1] * v.y + r[2] * v.z;
+        Vec3::new(row(&self.m[0]), row(&self.m[1]), row(&self.m[2]))
+    }
+
+    /// Transform a surface normal, with the inverse transpose matrix
+    /// so that it stays perpendicular to the transformed surface.
+    /// The result is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let inv = &self.inv;
+        let column = |j: usize| inv[0][j] * n.x + inv[1][j] * n.y + inv[2][j] * n.z;
+        Vec3::new(column(0), column(1), column(2))
+    }
+
+    /// Determinant of the linear part, by which volumes are scaled.
+    fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// The box around the transformed `bbox`.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        if !bbox.is_bounded() {
+            return Aabb::universe();
+        }
+        (0..8).fold(Aabb::empty(), |acc, corner| {
+            let pick = |axis: usize| {
+                let interval = bbox.axis(axis);
+                if corner & (1 << axis) == 0 {
+                    interval.min
+                } else {
+                    interval.max
+                }
+            };
+            acc.union_point(self.point(Point3::new(pick(0), pick(1), pick(2))))
+        })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// An object placed in the world by a [`Transform`].  Wrapping an
+/// `Arc` of an object, like a mesh, instances it without copying.
+/// An instance of a light can be sampled like the light itself.
+/// ```
+/// use lib::{BoxShape, Hittable, Interval, Lambertian, Point3, Ray, Transform, Transformed, Vec3};
+/// use std::sync::Arc;
+///
+/// let material = Arc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
+/// let cube = Arc::new(BoxShape::new(Point3::zero(), Point3::new(1.0, 1.0, 1.0), material));
+/// let moved = Transformed::new(cube.clone(), Transform::identity().translate(Vec3::new(5.0, 0.0, 0.0)));
+/// let spun = Transformed::new(cube, Transform::identity().rotate_y(45.0));
+///
+/// let ray = Ray::new(Point3::new(5.5, 0.5, 10.0), Vec3::new(0.0, 0.0, -1.0));
+/// assert!(moved.hit(&ray, Interval::positive_or_null()).unwrap().t == 9.0);
+/// let ray = Ray::new(Point3::new(0.5, 0.5, 10.0), Vec3::new(0.0, 0.0, -1.0));
+/// let hit = spun.hit(&ray, Interval::positive_or_null()).unwrap();
+/// assert!((hit.t - 9.5).abs() < 1e-9);
+/// assert!((hit.normal - Vec3::new(-1.0, 0.0, 1.0).unit_vector()).length() < 1e-9);
+/// ```
+pub struct Transformed<H> {
+    object: H,
+    transform: Transform,
+    bbox: Aabb,
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(object: H, transform: Transform) -> Transformed<H> {
+        let bbox = transform.bounding_box(&object.bounding_box());
+        Transformed {
+            object,
+            transform,
+            bbox,
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        // The direction is not normalized, so that distances along
+        // the ray are the same in both spaces.
+        let inverse = self.transform.inverse();
+        let object_ray = Ray::new(inverse.point(ray.origin), inverse.vector(ray.direction));
+
+        let mut hit = self.object.hit(&object_ray, ray_t)?;
+        hit.p = self.transform.point(hit.p);
+        hit.normal = self.transform.normal(hit.normal).unit_vector();
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let inverse = self.transform.inverse();
+        let (object_origin, object_direction) = (inverse.point(origin), inverse.vector(direction));
+        let pdf = self.object.pdf_value(object_origin, object_direction);
+        // Densities are per solid angle, which the transformation
+        // stretches unless it is a similarity: by |det A| |A⁻¹ω|³ at
+        // the unit direction ω.
+        let stretch = object_direction.length() / direction.length();
+        pdf / (self.transform.determinant().abs() * stretch.powi(3))
+    }
+
+    fn random(&self, origin: Point3, rng: &mut Sampler) -> Option<Vec3> {
+        let inverse = self.transform.inverse();
+        let direction = self.object.random(inverse.point(origin), rng)?;
+        Some(self.transform.vector(direction))
+    }
+}
+
+#[test]
+fn transform_test() {
+    let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-12;
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    let z = Vec3::new(0.0, 0.0, 1.0);
+
+    assert!(close(Transform::identity().rotate_x(90.0).vector(y), z));
+    assert!(close(Transform::identity().rotate_y(90.0).vector(z), x));
+    assert!(close(Transform::identity().rotate_z(90.0).vector(x), y));
+    assert!(close(
+        Transform::identity()
+            .rotate(Vec3::new(1.0, 1.0, 1.0), 120.0)
+            .vector(x),
+        y
+    ));
+
+    // Translations move points but not directions.
+    let t = Transform::identity().translate(Vec3::new(1.0, 2.0, 3.0));
+    assert!(close(t.point(Vec3::zero()), Vec3::new(1.0, 2.0, 3.0)));
+    assert!(close(t.vector(x), x));
+
+    // Normals stay perpendicular to surfaces under non-uniform scaling:
+    // the x + y = 1 plane becomes x/2 + y = 1.
+    let t = Transform::identity().scale(Vec3::new(2.0, 1.0, 1.0));
+    let n = t.normal(Vec3::new(1.0, 1.0, 0.0));
+    let tangent = t.vector(Vec3::new(1.0, -1.0, 0.0));
+    assert!(n.dot(tangent).abs() < 1e-12);
+
+    let t = Transform::identity()
+        .rotate_y(30.0)
+        .scale(Vec3::new(1.0, 3.0, 0.5))
+        .translate(x);
+    let p = Vec3::new(0.3, -2.0, 7.0);
+    assert!(close(t.then(t.inverse()).point(p), p));
+    assert!(close(t.inverse().then(t).point(p), p));
+
+    let bbox = Transform::identity()
+        .rotate_z(45.0)
+        .bounding_box(&Aabb::from_points(Vec3::zero(), Vec3::new(1.0, 1.0, 1.0)));
+    assert!((bbox.x.min + 0.5f64.sqrt()).abs() < 1e-12 && (bbox.y.max - 2f64.sqrt()).abs() < 1e-12);
+    assert!(!t.bounding_box(&Aabb::universe()).is_bounded());
+}
+
+#[test]
+fn transformed_light_test() {
+    use crate::sampler::pixel_sampler;
+    use crate::{Color, Emissive, Quad, Sphere};
+    use std::sync::Arc;
+
+    // A transformed light is sampled like the same light built in
+    // place, even under non-uniform scaling.
+    let lamp = Arc::new(Emissive::new(Color::new(1.0, 1.0, 1.0), 1.0));
+    let (q, u, v) = (
+        Point3::new(-0.5, 2.0, -0.5),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    );
+    let t = Transform::identity()
+        .scale(Vec3::new(3.0, 1.0, 0.5))
+        .rotate_x(20.0)
+        .translate(Vec3::new(0.5, 1.0, 0.0));
+    let instance = Transformed::new(Quad::new(q, u, v, lamp.clone()), t);
+    let in_place = Quad::new(t.point(q), t.vector(u), t.vector(v), lamp.clone());
+
+    let t = Transform::identity()
+        .scale(Vec3::new(2.0, 2.0, 2.0))
+        .translate(Vec3::new(0.0, 3.0, -1.0));
+    let ball = Transformed::new(Sphere::new(Point3::zero(), 0.5, lamp.clone()), t);
+    let ball_in_place = Sphere::new(Point3::new(0.0, 3.0, -1.0), 1.0, lamp);
+
+    let mut rng = pixel_sampler(0, 0);
+    let origin = Point3::new(0.2, -0.3, 0.1);
+    for (light, expected) in [
+        (&instance as &dyn Hittable, &in_place as &dyn Hittable),
+        (&ball, &ball_in_place),
+    ] {
+        for _ in 0..20 {
+            let direction = light.random(origin, &mut rng).unwrap();
+            let pdf = light.pdf_value(origin, direction);
+            assert!(pdf > 0.0);
+            assert!((pdf / expected.pdf_value(origin, direction) - 1.0).abs() < 1e-9);
+            // Densities don't depend on the length of the direction.
+            assert!((light.pdf_value(origin, 3.0 * direction) / pdf - 1.0).abs() < 1e-9);
+        }
+    }
+    assert!(instance.pdf_value(origin, Vec3::new(0.0, -1.0, 0.0)) == 0.0);
+}
@@ -0,0 +1,169 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// A triangle, optionally with per-vertex normals for smooth shading
+/// and per-vertex texture coordinates.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[[f64; 2]; 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    /// Shade with the normals interpolated from those of the vertices.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Texture coordinates of the vertices.  Without them, the
+    /// barycentric coordinates of hits are used.
+    pub fn with_uvs(mut self, uvs: [[f64; 2]; 3]) -> Triangle {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<Hit<'_>> {
+        let (t, b1, b2) = intersect(ray, ray_t, &self.vertices)?;
+        Some(shade(
+            ray,
+            t,
+            [b1, b2],
+            &self.vertices,
+            self.normals.as_ref(),
+            self.uvs.as_ref(),
+            self.material.as_ref(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        bounding_box(&self.vertices)
+    }
+}
+
+/// Möller–Trumbore ray/triangle intersection.  Returns the distance
+/// along the ray and the barycentric weights of the second and third
+/// vertices.
+pub(crate) fn intersect(
+    ray: &Ray,
+    ray_t: Interval,
+    [p0, p1, p2]: &[Point3; 3],
+) -> Option<(f64, f64, f64)> {
+    let edge1 = *p1 - *p0;
+    let edge2 = *p2 - *p0;
+    let pvec = ray.direction.cross(edge2);
+    let det = edge1.dot(pvec);
+    // The ray is parallel to the triangle.
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin - *p0;
+    let b1 = tvec.dot(pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(edge1);
+    let b2 = ray.direction.dot(qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(qvec) * inv_det;
+    ray_t.surrounds(t).then_some((t, b1, b2))
+}
+
+/// Build the hit at barycentric weights `[b1, b2]` of a triangle.
+/// `front_face` follows the geometric normal, whose side the
+/// interpolated shading normal is kept on.
+pub(crate) fn shade<'a>(
+    ray: &Ray,
+    t: f64,
+    [b1, b2]: [f64; 2],
+    [p0, p1, p2]: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: Option<&[[f64; 2]; 3]>,
+    material: &'a dyn Material,
+) -> Hit<'a> {
+    let b0 = 1.0 - b1 - b2;
+    let (u, v) = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            b0 * uv0[0] + b1 * uv1[0] + b2 * uv2[0],
+            b0 * uv0[1] + b1 * uv1[1] + b2 * uv2[1],
+        ),
+        None => (b1, b2),
+    };
+    let geometric = (*p1 - *p0).cross(*p2 - *p0).unit_vector();
+    let mut hit = Hit::new(ray, t, geometric, material, u, v);
+
+    if let Some([n0, n1, n2]) = normals {
+        let shading = (b0 * *n0 + b1 * *n1 + b2 * *n2).unit_vector();
+        if !shading.x.is_nan() {
+            hit.normal = if shading.dot(hit.normal) < 0.0 {
+                -shading
+            } else {
+                shading
+            };
+        }
+    }
+    hit
+}
+
+/// The box around a triangle, padded since triangles are flat.
+pub(crate) fn bounding_box([p0, p1, p2]: &[Point3; 3]) -> Aabb {
+    Aabb::from_points(*p0, *p1)
+        .union_point(*p2)
+        .pad_to_minimums(1e-4)
+}
+
+#[test]
+fn triangle_test() {
+    use crate::{Color, Lambertian};
+
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let triangle = Triangle::new(
+        Point3::new(-1.0, -1.0, -2.0),
+        Point3::new(1.0, -1.0, -2.0),
+        Point3::new(-1.0, 1.0, -2.0),
+        material,
+    );
+
+    let ray = Ray::new(Point3::zero(), Vec3::new(-0.25, -0.25, -1.0));
+    let hit = triangle.hit(&ray, Interval::positive_or_null()).unwrap();
+    assert!((hit.t - 2.0).abs() < 1e-12);
+    assert!(hit.front_face && hit.normal == Vec3::new(0.0, 0.0, 1.0));
+    assert!((hit.u - 0.25).abs() < 1e-12 && (hit.v - 0.25).abs() < 1e-12);
+
+    // Outside of the triangle, past its hypotenuse
+    let ray = Ray::new(Point3::zero(), Vec3::new(0.25, 0.25, -1.0));
+    assert!(triangle.hit(&ray, Interval::positive_or_null()).is_none());
+    // Parallel to it
+    let ray = Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0));
+    assert!(triangle.hit(&ray, Interval::universe()).is_none());
+
+    // From behind, with smooth normals tilted towards +x
+    let tilted = Vec3::new(1.0, 0.0, 1.0).unit_vector();
+    let triangle = triangle.with_normals([tilted; 3]);
+    let ray = Ray::new(Point3::new(-0.5, -0.5, -4.0), Vec3::new(0.0, 0.0, 1.0));
+    let hit = triangle.hit(&ray, Interval::positive_or_null()).unwrap();
+    assert!(!hit.front_face);
+    assert!((hit.normal + tilted).length() < 1e-12);
+}
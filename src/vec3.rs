@@ -1,6 +1,6 @@
 use rand::prelude::*;
 use std::f64;
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
 #[derive(PartialEq, PartialOrd, Clone, Copy, Debug)]
 pub struct Vec3 {
@@ -21,13 +21,11 @@ impl Vec3 {
         Self::new(0.0, 0.0, 0.0)
     }
 
-    pub fn random() -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
         Vec3::new(rng.gen(), rng.gen(), rng.gen())
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random_range<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> Vec3 {
         Vec3::new(
             rng.gen_range(min..max),
             rng.gen_range(min..max),
@@ -35,21 +33,31 @@ impl Vec3 {
         )
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
+    pub fn random_in_unit_sphere<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
         loop {
-            let ret = Self::random_range(-1.0, 1.0);
+            let ret = Self::random_range(rng, -1.0, 1.0);
             if ret.length_squared() < 1.0 {
                 return ret;
             }
         }
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        Self::random_in_unit_sphere().unit_vector()
+    /// A random point in the unit disk of the XY plane (z is always 0)
+    pub fn random_in_unit_disk<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+        loop {
+            let ret = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
+            if ret.length_squared() < 1.0 {
+                return ret;
+            }
+        }
     }
 
-    pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
-        let on_unit_sphere = Self::random_unit_vector();
+    pub fn random_unit_vector<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+        Self::random_in_unit_sphere(rng).unit_vector()
+    }
+
+    pub fn random_on_hemisphere<R: Rng + ?Sized>(rng: &mut R, normal: &Vec3) -> Vec3 {
+        let on_unit_sphere = Self::random_unit_vector(rng);
         if on_unit_sphere.dot(*normal) > 0.0 {
             // In the same hemisphere as the normal
             on_unit_sphere
@@ -85,6 +93,33 @@ impl Vec3 {
     pub fn unit_vector(self) -> Vec3 {
         self / self.length()
     }
+
+    /// Return true if the vector is close to zero in all dimensions.
+    pub fn near_zero(self) -> bool {
+        let s = 1e-8;
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
+    }
+
+    /// Mirror reflection of this vector about the surface normal `n`
+    /// (which must be a unit vector).
+    /// ```
+    /// use lib::Vec3;
+    /// let v = Vec3::new(1.0, -1.0, 0.0);
+    /// assert_eq!(v.reflect(Vec3::new(0.0, 1.0, 0.0)), Vec3::new(1.0, 1.0, 0.0));
+    /// ```
+    pub fn reflect(self, n: Vec3) -> Vec3 {
+        self - 2.0 * self.dot(n) * n
+    }
+
+    /// Refraction of this unit vector through a surface of normal `n`,
+    /// `etai_over_etat` being the ratio of the refractive indices
+    /// (Snell's law).
+    pub fn refract(self, n: Vec3, etai_over_etat: f64) -> Vec3 {
+        let cos_theta = (-self).dot(n).min(1.0);
+        let r_out_perp = etai_over_etat * (self + cos_theta * n);
+        let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
+        r_out_perp + r_out_parallel
+    }
 }
 
 impl Default for Vec3 {
@@ -93,6 +128,25 @@ impl Default for Vec3 {
     }
 }
 
+/// Access a coordinate by axis number (0 is x, 1 is y, 2 is z).
+/// ```
+/// use lib::Vec3;
+/// let v = Vec3::new(1.0, 2.0, 3.0);
+/// assert! (v[0] == 1.0 && v[1] == 2.0 && v[2] == 3.0);
+/// ```
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Bad axis: {axis}"),
+        }
+    }
+}
+
 impl Add for Vec3 {
     type Output = Vec3;
 
@@ -144,7 +198,7 @@ impl Div<f64> for Vec3 {
     type Output = Vec3;
 
     fn div(self, rhs: f64) -> Self::Output {
-        self.clone() * (1.0 / rhs)
+        self * (1.0 / rhs)
     }
 }
 